@@ -1,9 +1,12 @@
+use crate::store::blockstore::{BlockStore, PagedBlockStore};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::{SchematicInputStream, SchematicOutputStream};
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::Sub;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::string::ToString;
 use std::sync::OnceLock;
 
@@ -17,8 +20,8 @@ pub enum Axis {
 /// The order in which axes are iterated or indexed.
 /// For example, `XYZ` means X is the outermost axis, then Y, then Z is the innermost axis.
 /// So in `XYZ` order, X changes the slowest, and Z changes the fastest.
-
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AxisOrder {
     XYZ,
     XZY,
@@ -29,6 +32,7 @@ pub enum AxisOrder {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Boundary {
     pub min_x: i32,
     pub min_y: i32,
@@ -91,10 +95,28 @@ impl Display for BlockState {
     }
 }
 
+/// Serialized as the block state string, e.g. `"minecraft:oak_stairs[facing=north,half=top]"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BlockState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BlockState {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let input = String::deserialize(deserializer)?;
+        BlockState::from_string(input).map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Block {
     pub position: BlockPosition,
     pub state: Rc<BlockState>,
+    /// The biome at this position, for formats that store one (Sponge v3 `Biomes`).
+    pub biome: Option<Rc<str>>,
 }
 
 impl Display for Block {
@@ -112,6 +134,7 @@ impl Debug for Block {
         f.debug_struct("Block")
             .field("state", &self.state)
             .field("position", &self.position)
+            .field("biome", &self.biome)
             .finish()
     }
 }
@@ -143,18 +166,20 @@ impl AxisOrder {
         AxisOrder::XYZ
     }
 
-    pub fn index(&self, pos: &BlockPosition, boundary: &Boundary) -> i32 {
-        let mut index = 0;
+    /// Flat index of `pos` inside `boundary` for this order.
+    /// Computed in i64, as large boundaries easily exceed `i32::MAX` cells.
+    pub fn index(&self, pos: &BlockPosition, boundary: &Boundary) -> i64 {
+        let mut index: i64 = 0;
         for axis in self.axis() {
             let coord = match axis {
-                Axis::X => pos.x - boundary.min_x,
-                Axis::Y => pos.y - boundary.min_y,
-                Axis::Z => pos.z - boundary.min_z,
+                Axis::X => pos.x as i64 - boundary.min_x as i64,
+                Axis::Y => pos.y as i64 - boundary.min_y as i64,
+                Axis::Z => pos.z as i64 - boundary.min_z as i64,
             };
             let dim = match axis {
-                Axis::X => boundary.d_x,
-                Axis::Y => boundary.d_y,
-                Axis::Z => boundary.d_z,
+                Axis::X => boundary.d_x as i64,
+                Axis::Y => boundary.d_y as i64,
+                Axis::Z => boundary.d_z as i64,
             };
             index = index * dim + coord;
         }
@@ -163,6 +188,8 @@ impl AxisOrder {
 }
 
 impl Boundary {
+    /// Does not validate its input: negative dimensions are stored as given and make `volume`,
+    /// `contains` and iteration meaningless. Prefer `try_new` for untrusted sizes.
     pub fn new(min_x: i32, min_y: i32, min_z: i32, d_x: i32, d_y: i32, d_z: i32) -> Self {
         Boundary {
             min_x,
@@ -174,6 +201,30 @@ impl Boundary {
         }
     }
 
+    /// Like `new`, but rejects negative dimensions. Zero-sized boundaries are allowed.
+    pub fn try_new(min_x: i32, min_y: i32, min_z: i32, d_x: i32, d_y: i32, d_z: i32) -> Result<Self, String> {
+        if d_x < 0 || d_y < 0 || d_z < 0 {
+            return Err(format!("Boundary dimensions must not be negative, got {}x{}x{}", d_x, d_y, d_z));
+        }
+        Ok(Boundary::new(min_x, min_y, min_z, d_x, d_y, d_z))
+    }
+
+    /// Like `new_from_min_max`, but rejects a max below its min and spans that overflow an `i32`.
+    pub fn try_new_from_min_max(
+        min_x: i32,
+        min_y: i32,
+        min_z: i32,
+        max_x: i32,
+        max_y: i32,
+        max_z: i32,
+    ) -> Result<Self, String> {
+        let span = |min: i32, max: i32| {
+            i32::try_from(max as i64 - min as i64 + 1)
+                .map_err(|_| format!("Boundary span from {} to {} does not fit in an i32", min, max))
+        };
+        Boundary::try_new(min_x, min_y, min_z, span(min_x, max_x)?, span(min_y, max_y)?, span(min_z, max_z)?)
+    }
+
     pub fn new_empty() -> Self {
         Boundary {
             min_x: 0,
@@ -225,8 +276,25 @@ impl Boundary {
         }
     }
 
+    /// Smallest boundary enclosing every block position, or `new_empty` for an empty slice.
+    pub fn from_blocks(blocks: &[Block]) -> Self {
+        Self::from_positions(blocks.iter().map(|block| block.position))
+    }
+
+    /// Smallest boundary enclosing every position, or `new_empty` if there are none.
+    pub fn from_positions(mut positions: impl Iterator<Item = BlockPosition>) -> Self {
+        let Some(first) = positions.next() else {
+            return Boundary::new_empty();
+        };
+        let (min, max) = positions.fold((first, first), |(min, max), pos| (
+            BlockPosition::new(min.x.min(pos.x), min.y.min(pos.y), min.z.min(pos.z)),
+            BlockPosition::new(max.x.max(pos.x), max.y.max(pos.y), max.z.max(pos.z)),
+        ));
+        Boundary::new_from_positions(&min, &max)
+    }
+
     pub fn volume(&self) -> usize {
-        (self.d_x * self.d_y * self.d_z) as usize
+        (self.d_x as i64 * self.d_y as i64 * self.d_z as i64) as usize
     }
 
     fn min_x(&self) -> i32 {
@@ -293,6 +361,27 @@ impl Boundary {
         vec![self.d_x, self.d_y, self.d_z]
     }
 
+    /// Returns the same box with non-negative dimensions.
+    /// A negative extent covers `[min + d, min)` on its axis, so the minimum moves down by `|d|`.
+    pub fn normalized(&self) -> Boundary {
+        fn axis(min: i32, d: i32) -> (i32, i32) {
+            if d < 0 { (min + d, -d) } else { (min, d) }
+        }
+        let (min_x, d_x) = axis(self.min_x, self.d_x);
+        let (min_y, d_y) = axis(self.min_y, self.d_y);
+        let (min_z, d_z) = axis(self.min_z, self.d_z);
+        Boundary::new(min_x, min_y, min_z, d_x, d_y, d_z)
+    }
+
+    /// Splits the boundary into the same box moved to the origin and the offset it was moved by.
+    /// Adding the offset back to the returned boundary's min yields the original.
+    pub fn recentered_to_origin(&self) -> (Boundary, BlockPosition) {
+        (
+            Boundary::new_from_size(self.d_x, self.d_y, self.d_z),
+            BlockPosition::new(self.min_x, self.min_y, self.min_z),
+        )
+    }
+
     pub fn contains(&self, pos: &BlockPosition) -> bool {
         pos.x >= self.min_x
             && pos.x < self.min_x + self.d_x
@@ -319,6 +408,7 @@ impl Boundary {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockPosition {
     x: i32,
     y: i32,
@@ -334,6 +424,25 @@ impl BlockPosition {
         [self.x, self.y, self.z]
     }
 
+    /// Coordinates of the cubic chunk containing this position.
+    /// Uses floor division, so (-1, -1, -1) lies in chunk (-1, -1, -1) rather than (0, 0, 0).
+    pub fn chunk_coords(&self, chunk_size: i32) -> (i32, i32, i32) {
+        (
+            self.x.div_euclid(chunk_size),
+            self.y.div_euclid(chunk_size),
+            self.z.div_euclid(chunk_size),
+        )
+    }
+
+    /// Offset of this position inside its chunk, each component in `0..chunk_size`.
+    pub fn local_coords(&self, chunk_size: i32) -> (i32, i32, i32) {
+        (
+            self.x.rem_euclid(chunk_size),
+            self.y.rem_euclid(chunk_size),
+            self.z.rem_euclid(chunk_size),
+        )
+    }
+
     fn select(&self, axis: &Axis) -> i32 {
         match axis {
             Axis::X => self.x,
@@ -392,7 +501,9 @@ impl BlockPosition {
 }
 
 impl BlockState {
-    pub fn new(name: String, properties: Vec<(String, String)>) -> Self {
+    /// Properties are sorted by key, so states built with a different property order are equal.
+    pub fn new(name: String, mut properties: Vec<(String, String)>) -> Self {
+        properties.sort_by(|(a, _), (b, _)| a.cmp(b));
         let hash = BlockState::hash(&name, &properties);
         BlockState {
             name,
@@ -409,6 +520,24 @@ impl BlockState {
         &self.name
     }
 
+    /// The part of the name before `:`, `"minecraft"` for names without a namespace.
+    pub fn namespace(&self) -> &str {
+        match self.split_name() {
+            ("", _) => "minecraft",
+            (namespace, _) => namespace,
+        }
+    }
+
+    /// The part of the name after `:`, e.g. `"oak_log"` for `minecraft:oak_log`.
+    pub fn path(&self) -> &str {
+        self.split_name().1
+    }
+
+    /// Splits the name on its first `:`, with an empty namespace if there is none.
+    fn split_name(&self) -> (&str, &str) {
+        self.name.split_once(':').unwrap_or(("", &self.name))
+    }
+
     pub fn properties(&self) -> &Vec<(String, String)> {
         &self.properties
     }
@@ -424,6 +553,98 @@ impl BlockState {
         Some(map)
     }
 
+    /// Returns a copy with `key` set to `value`, replacing any existing value.
+    pub fn with_property(&self, key: &str, value: &str) -> BlockState {
+        let mut properties = self.properties.clone();
+        match properties.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => properties.push((key.to_string(), value.to_string())),
+        }
+        BlockState::new(self.name.clone(), properties)
+    }
+
+    /// Returns a copy without `key`, or an unchanged copy if the property is absent.
+    pub fn without_property(&self, key: &str) -> BlockState {
+        let properties = self
+            .properties
+            .iter()
+            .filter(|(k, _)| k != key)
+            .cloned()
+            .collect();
+        BlockState::new(self.name.clone(), properties)
+    }
+
+    /// Returns the state as it looks after mirroring across `axis`.
+    /// `facing` is flipped along that axis, and for X/Z mirrors the left/right handedness of
+    /// door `hinge` and stair `shape` is swapped. Other properties are left as-is.
+    pub fn mirrored(&self, axis: Axis) -> BlockState {
+        let properties = self
+            .properties
+            .iter()
+            .map(|(k, v)| {
+                let flipped = match (k.as_str(), axis) {
+                    ("facing", _) => Self::mirrored_facing(v, axis),
+                    ("hinge", Axis::X | Axis::Z) => match v.as_str() {
+                        "left" => Some("right"),
+                        "right" => Some("left"),
+                        _ => None,
+                    },
+                    ("shape", Axis::X | Axis::Z) => match v.as_str() {
+                        "inner_left" => Some("inner_right"),
+                        "inner_right" => Some("inner_left"),
+                        "outer_left" => Some("outer_right"),
+                        "outer_right" => Some("outer_left"),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                (k.clone(), flipped.map_or_else(|| v.clone(), str::to_string))
+            })
+            .collect();
+        BlockState::new(self.name.clone(), properties)
+    }
+
+    /// Returns a copy with tool-specific spellings of values normalized: boolean-like values are
+    /// lowercased (`True` -> `true`) and integer-like values lose leading zeros (`01` -> `1`).
+    /// Any other value is left as-is.
+    pub fn canonicalize(&self) -> BlockState {
+        let properties = self
+            .properties
+            .iter()
+            .map(|(k, v)| (k.clone(), Self::canonical_value(v)))
+            .collect();
+        BlockState::new(self.name.clone(), properties)
+    }
+
+    fn canonical_value(value: &str) -> String {
+        if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+            return value.to_ascii_lowercase();
+        }
+        let (sign, digits) = match value.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", value),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return value.to_string();
+        }
+        match digits.trim_start_matches('0') {
+            "" => "0".to_string(),
+            trimmed => format!("{}{}", sign, trimmed),
+        }
+    }
+
+    fn mirrored_facing(facing: &str, axis: Axis) -> Option<&'static str> {
+        match (facing, axis) {
+            ("east", Axis::X) => Some("west"),
+            ("west", Axis::X) => Some("east"),
+            ("up", Axis::Y) => Some("down"),
+            ("down", Axis::Y) => Some("up"),
+            ("north", Axis::Z) => Some("south"),
+            ("south", Axis::Z) => Some("north"),
+            _ => None,
+        }
+    }
+
     fn hash(name: &String, properties: &Vec<(String, String)>) -> u64 {
         let mut hasher = DefaultHasher::new();
         name.hash(&mut hasher);
@@ -479,6 +700,29 @@ impl BlockState {
             || self.name == "minecraft:void_air"
     }
 
+    /// Like `is_air`, but also treats `minecraft:structure_void` as empty, plus
+    /// `minecraft:barrier` when `include_barrier` is set.
+    pub fn is_empty_like(&self, include_barrier: bool) -> bool {
+        self.is_air()
+            || self.name == "minecraft:structure_void"
+            || (include_barrier && self.name == "minecraft:barrier")
+    }
+
+    /// Checks this state against a pattern.
+    /// A bare name like `minecraft:oak_log` matches every state of that name regardless of properties,
+    /// a full state string like `minecraft:oak_log[axis=x]` only matches exactly.
+    pub fn matches(&self, pattern: &str) -> bool {
+        if !pattern.contains('[') {
+            return self.name == pattern.trim();
+        }
+        match BlockState::from_str(pattern) {
+            Ok(state) => self.name == state.name
+                && self.properties.len() == state.properties.len()
+                && state.properties.iter().all(|p| self.properties.contains(p)),
+            Err(_) => false,
+        }
+    }
+
     pub fn to_string(&self) -> String {
         if self.properties.is_empty() {
             self.name.clone()
@@ -586,26 +830,8 @@ impl BlockState {
     pub fn difference(&self, other: &BlockState) -> String {
         let mut sb = String::with_capacity(64);
         if self.name != other.name {
-            let other_namespace = if let Some(idx) = other.name.find(':') {
-                &other.name[..idx]
-            } else {
-                ""
-            };
-            let other_type = if let Some(idx) = other.name.find(':') {
-                &other.name[idx + 1..]
-            } else {
-                &other.name
-            };
-            let self_namespace = if let Some(idx) = self.name.find(':') {
-                &self.name[..idx]
-            } else {
-                ""
-            };
-            let self_type = if let Some(idx) = self.name.find(':') {
-                &self.name[idx + 1..]
-            } else {
-                &self.name
-            };
+            let (other_namespace, other_type) = other.split_name();
+            let (self_namespace, self_type) = self.split_name();
 
             if other_namespace != self_namespace {
                 sb.push_str(other_namespace);
@@ -656,7 +882,17 @@ impl BlockState {
         BlockState::from_string(input.to_string())
     }
 
-    pub fn from_string(mut input: String) -> Result<BlockState, String> {
+    pub fn from_string(input: String) -> Result<BlockState, String> {
+        BlockState::parse(input, false)
+    }
+
+    /// Like `from_string`, but rejects input the lenient parser silently accepts:
+    /// repeated property keys and segments that are not a single `key=value` pair.
+    pub fn from_string_strict(input: String) -> Result<BlockState, String> {
+        BlockState::parse(input, true)
+    }
+
+    fn parse(mut input: String, strict: bool) -> Result<BlockState, String> {
         if input.len() > 4096 {
             return Err(format!(
                 "Malformed BlockState string: length {} exceeds maximum of 4096",
@@ -721,6 +957,27 @@ impl BlockState {
         }
 
         let type_properties_string = &input[split_index + 1..];
+        if strict && type_properties_string != "]" {
+            let mut seen_keys = HashSet::new();
+            for kv in type_properties_string[0..type_properties_string.len() - 1].split(",") {
+                if kv.matches('=').count() != 1 {
+                    return Err(format!(
+                        "Malformed BlockState string: property '{}' is not a single key=value pair",
+                        kv
+                    ));
+                }
+                let (key, value) = kv.split_once('=').unwrap();
+                if key.is_empty() || value.is_empty() {
+                    return Err(format!(
+                        "Malformed BlockState string: property '{}' has an empty key or value",
+                        kv
+                    ));
+                }
+                if !seen_keys.insert(key) {
+                    return Err(format!("Malformed BlockState string: duplicate property key '{}'", key));
+                }
+            }
+        }
         let property_map = if type_properties_string == "]" {
             vec![]
         } else {
@@ -767,6 +1024,35 @@ impl BlockState {
     }
 }
 
+/// Per-thread registry handing out shared `Rc`s for equal block states,
+/// so identical states parsed from different palettes share one allocation.
+pub struct BlockStateRegistry;
+
+impl BlockStateRegistry {
+    thread_local! {
+      static INTERNED: std::cell::RefCell<HashMap<u64, Weak<BlockState>>> = std::cell::RefCell::new(HashMap::new());
+    }
+
+    pub fn intern(state: BlockState) -> Rc<BlockState> {
+        Self::INTERNED.with(|interned| {
+            let mut interned = interned.borrow_mut();
+            if let Some(existing) = interned.get(&state.cached_hash).and_then(Weak::upgrade) {
+                if *existing == state {
+                    return existing;
+                }
+                // hash collision with a different live state, leave it uninterned
+                return Rc::new(state);
+            }
+            if interned.len() >= 1024 && interned.len().is_power_of_two() {
+                interned.retain(|_, weak| weak.strong_count() > 0);
+            }
+            let rc = Rc::new(state);
+            interned.insert(rc.cached_hash, Rc::downgrade(&rc));
+            rc
+        })
+    }
+}
+
 impl Sub for BlockState {
     type Output = String;
 
@@ -777,15 +1063,27 @@ impl Sub for BlockState {
 
 impl Block {
     pub fn new(state: Rc<BlockState>, position: BlockPosition) -> Self {
-        Block { state, position }
+        Block { state, position, biome: None }
     }
 
     pub fn new_at_zero(state: Rc<BlockState>) -> Self {
         Block {
             state,
             position: BlockPosition::zero(),
+            biome: None,
         }
     }
+
+    /// Returns this block with its biome set, e.g. `"minecraft:plains"`.
+    pub fn with_biome(mut self, biome: impl Into<Rc<str>>) -> Self {
+        self.biome = Some(biome.into());
+        self
+    }
+
+    /// Parses `state` with `BlockState::from_str` and places it at `position`.
+    pub fn from_state_str(state: &str, position: BlockPosition) -> Result<Self, String> {
+        Ok(Block::new(Rc::new(BlockState::from_str(state)?), position))
+    }
 }
 
 impl Region for Boundary {
@@ -803,6 +1101,37 @@ impl Region for Boundary {
     }
 }
 
+/// A region made of several boxes, e.g. two separate buildings.
+///
+/// Iteration walks the boxes in insertion order, each in the requested axis order.
+/// A position shared by several boxes is only yielded for the first box containing it.
+pub struct MultiRegion {
+    boundaries: Vec<Boundary>,
+}
+
+impl MultiRegion {
+    pub fn new(boundaries: Vec<Boundary>) -> Self {
+        MultiRegion { boundaries }
+    }
+
+    pub fn boundaries(&self) -> &[Boundary] {
+        &self.boundaries
+    }
+}
+
+impl Region for MultiRegion {
+    fn contains(&self, pos: &BlockPosition) -> bool {
+        self.boundaries.iter().any(|b| b.contains(pos))
+    }
+
+    fn iter(&self, axis_order: AxisOrder) -> Box<dyn Iterator<Item = BlockPosition> + '_> {
+        Box::new(self.boundaries.iter().enumerate().flat_map(move |(i, boundary)| {
+            let earlier = &self.boundaries[..i];
+            Region::iter(boundary, axis_order).filter(move |pos| !earlier.iter().any(|b| b.contains(pos)))
+        }))
+    }
+}
+
 struct BoundaryIterator<'a> {
     boundary: &'a Boundary,
     axis_order: AxisOrder,
@@ -895,9 +1224,417 @@ impl Iterator for BoundaryIterator<'_> {
     }
 }
 
+/// A whole schematic held in memory: a block store together with its fixed boundary.
+pub struct Schematic {
+    blocks: Box<dyn BlockStore>,
+    boundary: Boundary,
+}
+
+impl Schematic {
+    pub fn new(boundary: Boundary) -> Self {
+        Schematic {
+            blocks: Box::new(PagedBlockStore::new_for_fixed_boundary(boundary)),
+            boundary,
+        }
+    }
+
+    /// Loads every block of the stream. Streams without a declared boundary are sized to their content.
+    pub fn from_reader(reader: &mut dyn SchematicInputStream) -> Result<Self, SchematicError> {
+        let mut blocks: Box<dyn BlockStore> = match reader.boundary()? {
+            Some(boundary) => Box::new(PagedBlockStore::new_for_fixed_boundary(boundary)),
+            None => Box::new(PagedBlockStore::new_empty_resizable()),
+        };
+        reader.read_to_end(blocks.as_mut())?;
+        blocks.freeze_boundary();
+        let boundary = *blocks.boundary();
+        Ok(Schematic { blocks, boundary })
+    }
+
+    pub fn boundary(&self) -> &Boundary {
+        &self.boundary
+    }
+
+    pub fn blocks(&self) -> &dyn BlockStore {
+        self.blocks.as_ref()
+    }
+
+    pub fn get(&self, pos: &BlockPosition) -> Result<Option<Rc<BlockState>>, SchematicError> {
+        self.check_contains(pos)?;
+        Ok(self.blocks.block_at(pos)?)
+    }
+
+    pub fn set(&mut self, pos: &BlockPosition, state: Rc<BlockState>) -> Result<(), SchematicError> {
+        self.check_contains(pos)?;
+        Ok(self.blocks.set_block_at(pos, state)?)
+    }
+
+    fn check_contains(&self, pos: &BlockPosition) -> Result<(), SchematicError> {
+        if self.boundary.contains(pos) {
+            Ok(())
+        } else {
+            Err(SchematicError::OutOfBounds(format!("{} is outside the schematic boundary {:?}", pos, self.boundary)))
+        }
+    }
+
+    /// Writes all non-air blocks to the output stream and completes it.
+    pub fn write_to(&self, output: &mut dyn SchematicOutputStream) -> Result<(), SchematicError> {
+        output.set_boundary(self.boundary)?;
+        let mut batch = Vec::with_capacity(4096);
+        for (pos, state) in self.blocks.block_iterator(AxisOrder::XYZ) {
+            if let Some(state) = state {
+                batch.push(Block::new(state, pos));
+            }
+            if batch.len() == 4096 {
+                output.write(&batch)?;
+                batch.clear();
+            }
+        }
+        if !batch.is_empty() {
+            output.write(&batch)?;
+        }
+        output.complete()
+    }
+
+    /// Copies every block into `target`, shifted by `at`. Empty cells never touch the target,
+    /// and air only overwrites existing blocks when `skip_air` is false.
+    /// A fixed-size target must hold the whole shifted boundary, otherwise nothing is written.
+    pub fn paste_into(&self, target: &mut dyn BlockStore, at: BlockPosition, skip_air: bool) -> Result<(), SchematicError> {
+        let shifted = BlockPosition::new(self.boundary.min_x + at.x(), self.boundary.min_y + at.y(), self.boundary.min_z + at.z());
+        let shifted_max = BlockPosition::new(self.boundary.max_x() + at.x(), self.boundary.max_y() + at.y(), self.boundary.max_z() + at.z());
+        if !target.resizable()
+            && self.boundary.volume() > 0
+            && !(target.boundary().contains(&shifted) && target.boundary().contains(&shifted_max))
+        {
+            return Err(SchematicError::OutOfBounds(format!(
+                "Paste: {} to {} does not fit into the fixed target boundary {:?}",
+                shifted, shifted_max, target.boundary()
+            )));
+        }
+        // block_iterator leaves out air, which has to be pasted as well unless skip_air is set
+        for pos in self.blocks.iter(AxisOrder::XYZ) {
+            let state = match self.blocks.block_at(&pos)? {
+                Some(state) if !(skip_air && state.is_air()) => state,
+                _ => continue,
+            };
+            let target_pos = BlockPosition::new(pos.x() + at.x(), pos.y() + at.y(), pos.z() + at.z());
+            target.set_block_at(&target_pos, state)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::common::Region;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_is_empty_like() {
+        let state = |name: &str| super::BlockState::from_str(name).unwrap();
+        for name in ["minecraft:air", "minecraft:cave_air", "minecraft:void_air"] {
+            assert!(state(name).is_air(), "{}", name);
+            assert!(state(name).is_empty_like(false), "{}", name);
+        }
+        assert!(!state("minecraft:structure_void").is_air());
+        assert!(state("minecraft:structure_void").is_empty_like(false));
+        assert!(!state("minecraft:barrier").is_air());
+        assert!(!state("minecraft:barrier").is_empty_like(false));
+        assert!(state("minecraft:barrier").is_empty_like(true));
+        assert!(!state("minecraft:water[level=0]").is_empty_like(true));
+    }
+
+    #[test]
+    fn test_multi_region_visits_overlap_once() {
+        let first = super::Boundary::new(0, 0, 0, 4, 2, 4);
+        let second = super::Boundary::new(2, 1, 2, 4, 2, 4);
+        let region = super::MultiRegion::new(vec![first, second]);
+        let positions: Vec<super::BlockPosition> = region.iter(super::AxisOrder::XYZ).collect();
+        let unique: std::collections::HashSet<super::BlockPosition> = positions.iter().copied().collect();
+        // 32 + 32 positions with a 2x1x2 overlap
+        assert_eq!(positions.len(), 60);
+        assert_eq!(unique.len(), positions.len());
+        assert!(positions.iter().all(|p| region.contains(p)));
+        assert_eq!(positions[..32], first.iter(super::AxisOrder::XYZ).collect::<Vec<_>>()[..]);
+        assert!(!region.contains(&super::BlockPosition::new(5, 0, 0)));
+    }
+
+    #[test]
+    fn test_boundary_try_new() {
+        assert_eq!(super::Boundary::try_new(-4, 0, 2, 3, 2, 1), Ok(super::Boundary::new(-4, 0, 2, 3, 2, 1)));
+        assert_eq!(super::Boundary::try_new(0, 0, 0, 0, 0, 0), Ok(super::Boundary::new_empty()));
+        assert!(super::Boundary::try_new(0, 0, 0, -1, 2, 2).is_err());
+        assert!(super::Boundary::try_new(0, 0, 0, 2, 2, i32::MIN).is_err());
+
+        assert_eq!(
+            super::Boundary::try_new_from_min_max(-2, 0, 5, 1, 0, 5),
+            Ok(super::Boundary::new(-2, 0, 5, 4, 1, 1)),
+        );
+        assert_eq!(super::Boundary::try_new_from_min_max(3, 0, 0, 2, 0, 0).map(|b| b.volume()), Ok(0));
+        assert!(super::Boundary::try_new_from_min_max(3, 0, 0, 1, 0, 0).is_err());
+        assert!(super::Boundary::try_new_from_min_max(i32::MIN, 0, 0, i32::MAX, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_boundary_normalized() {
+        let boundary = super::Boundary::new(5, 0, 0, -3, 2, 1);
+        let normalized = boundary.normalized();
+        assert_eq!(normalized, super::Boundary::new(2, 0, 0, 3, 2, 1));
+        assert_eq!(normalized.volume(), 6);
+        for x in 0..8 {
+            let pos = super::BlockPosition::new(x, 1, 0);
+            assert_eq!(normalized.contains(&pos), (2..5).contains(&x), "x = {}", x);
+        }
+        assert_eq!(normalized.normalized(), normalized);
+        let all_negative = super::Boundary::new(0, 0, 0, -1, -2, -3).normalized();
+        assert_eq!(all_negative, super::Boundary::new(-1, -2, -3, 1, 2, 3));
+    }
+
+    #[test]
+    fn test_recentered_to_origin() {
+        let original = super::Boundary::new(10, -5, 3, 4, 2, 7);
+        let (recentered, offset) = original.recentered_to_origin();
+        assert_eq!(recentered, super::Boundary::new(0, 0, 0, 4, 2, 7));
+        assert_eq!(offset, super::BlockPosition::new(10, -5, 3));
+        let reconstructed = super::Boundary::new(
+            recentered.min_x + offset.x(),
+            recentered.min_y + offset.y(),
+            recentered.min_z + offset.z(),
+            recentered.d_x,
+            recentered.d_y,
+            recentered.d_z,
+        );
+        assert_eq!(reconstructed, original);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_boundary_serde_round_trip() {
+        let boundary = super::Boundary::new(-4, 0, 12, 16, 8, 3);
+        let json = serde_json::to_string(&boundary).unwrap();
+        assert_eq!(json, r#"{"min_x":-4,"min_y":0,"min_z":12,"d_x":16,"d_y":8,"d_z":3}"#);
+        assert_eq!(serde_json::from_str::<super::Boundary>(&json).unwrap(), boundary);
+
+        let pos = super::BlockPosition::new(1, -2, 3);
+        assert_eq!(serde_json::to_string(&pos).unwrap(), r#"{"x":1,"y":-2,"z":3}"#);
+        let order: super::AxisOrder = serde_json::from_str(r#""YZX""#).unwrap();
+        assert_eq!(order, super::AxisOrder::YZX);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_block_state_serde_round_trip() {
+        let state = super::BlockState::from_str("minecraft:oak_stairs[facing=north,half=top]").unwrap();
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(json, r#""minecraft:oak_stairs[facing=north,half=top]""#);
+        assert_eq!(serde_json::from_str::<super::BlockState>(&json).unwrap(), state);
+        assert!(serde_json::from_str::<super::BlockState>(r#""oak_stairs[facing=north]""#).is_err());
+    }
+
+    #[test]
+    fn test_block_from_state_str() {
+        let block = super::Block::from_state_str("minecraft:oak_stairs[half=top,facing=north]", super::BlockPosition::new(1, 2, 3)).unwrap();
+        assert_eq!(block.position, super::BlockPosition::new(1, 2, 3));
+        assert_eq!(block.state.name_ref(), "minecraft:oak_stairs");
+        assert_eq!(block.state.to_string(), "minecraft:oak_stairs[facing=north,half=top]");
+        assert_eq!(
+            block,
+            super::Block::new(Rc::new(super::BlockState::from_str("minecraft:oak_stairs[facing=north,half=top]").unwrap()), super::BlockPosition::new(1, 2, 3))
+        );
+        assert!(super::Block::from_state_str("stone[facing=north]", super::BlockPosition::zero()).is_err());
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let state = super::BlockState::from_str("minecraft:repeater[powered=True,delay=01]").unwrap();
+        let canonical = state.canonicalize();
+        assert_eq!(canonical.to_string(), "minecraft:repeater[delay=1,powered=true]");
+        assert_eq!(canonical, super::BlockState::from_str("minecraft:repeater[delay=1,powered=true]").unwrap());
+
+        let state = super::BlockState::from_str("minecraft:note_block[note=000,instrument=Harp,powered=FALSE,offset=-007]").unwrap();
+        assert_eq!(
+            state.canonicalize().to_string(),
+            "minecraft:note_block[instrument=Harp,note=0,offset=-7,powered=false]"
+        );
+    }
+
+    #[test]
+    fn test_property_order_is_canonical() {
+        let ab = super::BlockState::from_str("minecraft:oak_stairs[facing=north,half=top]").unwrap();
+        let ba = super::BlockState::from_str("minecraft:oak_stairs[half=top,facing=north]").unwrap();
+        assert_eq!(ab, ba);
+        assert_eq!(super::BlockState::hash(&ab.name, &ab.properties), super::BlockState::hash(&ba.name, &ba.properties));
+        assert_eq!(ab.to_string(), ba.to_string());
+        assert_eq!(ab.with_property("facing", "south"), ba.with_property("facing", "south"));
+
+        let mut palette = std::collections::HashMap::new();
+        for state in [ab, ba] {
+            let next_id = palette.len();
+            palette.entry(Rc::new(state)).or_insert(next_id);
+        }
+        assert_eq!(palette.len(), 1);
+    }
+
+    #[test]
+    fn test_from_string_strict() {
+        let lenient = super::BlockState::from_str("minecraft:stone[a=1,a=2]").unwrap();
+        assert_eq!(lenient.properties.len(), 2);
+        assert!(super::BlockState::from_string_strict("minecraft:stone[a=1,a=2]".to_string()).is_err());
+        assert!(super::BlockState::from_string_strict("minecraft:stone[a=1=2]".to_string()).is_err());
+        assert!(super::BlockState::from_string_strict("minecraft:stone[a=1,]".to_string()).is_err());
+        assert!(super::BlockState::from_string_strict("minecraft:stone[=1]".to_string()).is_err());
+        assert!(super::BlockState::from_string_strict("minecraft:stone[a=]".to_string()).is_err());
+        assert!(super::BlockState::from_string_strict("minecraft:stone[a=1,=]".to_string()).is_err());
+
+        let strict = super::BlockState::from_string_strict("minecraft:oak_log[axis=y,waterlogged=false]".to_string()).unwrap();
+        assert_eq!(strict, super::BlockState::from_str("minecraft:oak_log[axis=y,waterlogged=false]").unwrap());
+        assert!(super::BlockState::from_string_strict("minecraft:stone[]".to_string()).is_ok());
+        assert!(super::BlockState::from_string_strict("minecraft:stone".to_string()).is_ok());
+    }
+
+    #[test]
+    fn test_chunk_and_local_coords() {
+        let pos = super::BlockPosition::new(-1, -1, -1);
+        assert_eq!(pos.chunk_coords(16), (-1, -1, -1));
+        assert_eq!(pos.local_coords(16), (15, 15, 15));
+
+        let pos = super::BlockPosition::new(-16, 0, 33);
+        assert_eq!(pos.chunk_coords(16), (-1, 0, 2));
+        assert_eq!(pos.local_coords(16), (0, 0, 1));
+
+        let pos = super::BlockPosition::new(-17, 15, -32);
+        assert_eq!(pos.chunk_coords(16), (-2, 0, -2));
+        assert_eq!(pos.local_coords(16), (15, 15, 0));
+    }
+
+    #[test]
+    fn test_boundary_from_blocks() {
+        let stone = std::rc::Rc::new(super::BlockState::from_str("minecraft:stone").unwrap());
+        let block_at = |x, y, z| super::Block::new(stone.clone(), super::BlockPosition::new(x, y, z));
+
+        assert_eq!(super::Boundary::from_blocks(&[]), super::Boundary::new_empty());
+        assert_eq!(super::Boundary::from_blocks(&[block_at(4, -2, 9)]), super::Boundary::new(4, -2, 9, 1, 1, 1));
+
+        let scattered = [block_at(3, 0, -1), block_at(-5, 7, 2), block_at(0, -4, 10)];
+        let boundary = super::Boundary::from_blocks(&scattered);
+        assert_eq!(boundary, super::Boundary::new(-5, -4, -1, 9, 12, 12));
+        assert!(scattered.iter().all(|block| boundary.contains(&block.position)));
+    }
+
+    #[test]
+    fn test_with_and_without_property() {
+        let state = super::BlockState::from_str("minecraft:oak_stairs[facing=north]").unwrap();
+        let added = state.with_property("half", "top");
+        assert_eq!(added.to_string(), "minecraft:oak_stairs[facing=north,half=top]");
+        let replaced = added.with_property("facing", "east");
+        assert_eq!(replaced.to_string(), "minecraft:oak_stairs[facing=east,half=top]");
+        let removed = replaced.without_property("facing");
+        assert_eq!(removed.to_string(), "minecraft:oak_stairs[half=top]");
+        assert_eq!(removed.without_property("half").to_string(), "minecraft:oak_stairs");
+        assert_eq!(state.to_string(), "minecraft:oak_stairs[facing=north]");
+    }
+
+    #[test]
+    fn test_mirrored() {
+        let stairs = super::BlockState::from_str("minecraft:oak_stairs[facing=east,half=top,shape=inner_left]").unwrap();
+        let mirrored = stairs.mirrored(super::Axis::X);
+        assert_eq!(mirrored.to_string(), "minecraft:oak_stairs[facing=west,half=top,shape=inner_right]");
+        assert_eq!(mirrored.mirrored(super::Axis::X), stairs);
+        let along_z = stairs.mirrored(super::Axis::Z);
+        assert_eq!(along_z.to_string(), "minecraft:oak_stairs[facing=east,half=top,shape=inner_right]");
+
+        let door = super::BlockState::from_str("minecraft:oak_door[facing=north,hinge=left]").unwrap();
+        assert_eq!(door.mirrored(super::Axis::Z).to_string(), "minecraft:oak_door[facing=south,hinge=right]");
+        assert_eq!(door.mirrored(super::Axis::Y), door);
+    }
+
+    #[test]
+    fn test_schematic_from_sponge_to_vxl() {
+        use crate::stream::sponge_reader::SpongeSchematicInputStream;
+        use crate::stream::stream::SchematicInputStream;
+        use crate::stream::vxl_reader::VXLSchematicInputStream;
+        use crate::stream::vxl_writer::VXLSchematicOutputStream;
+        use flate2::read::GzDecoder;
+
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("stream/test_schematics/tree.sponge");
+        let mut reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        let mut schematic = super::Schematic::from_reader(&mut reader).unwrap();
+        let boundary = *schematic.boundary();
+        let origin = super::BlockPosition::new(boundary.min_x, boundary.min_y, boundary.min_z);
+        let glass = Rc::new(super::BlockState::from_str("minecraft:glass").unwrap());
+        schematic.set(&origin, glass.clone()).unwrap();
+        assert_eq!(schematic.get(&origin).unwrap(), Some(glass));
+        let outside = super::BlockPosition::new(boundary.min_x - 1, boundary.min_y, boundary.min_z);
+        assert!(matches!(schematic.get(&outside), Err(crate::stream::error::SchematicError::OutOfBounds(_))));
+
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, super::AxisOrder::XYZ, boundary);
+        schematic.write_to(&mut writer).unwrap();
+        let mut vxl = VXLSchematicInputStream::new(std::io::Cursor::new(data));
+        let reloaded = super::Schematic::from_reader(&mut vxl).unwrap();
+        assert_eq!(*reloaded.boundary(), boundary);
+        for pos in boundary.iter(super::AxisOrder::XYZ) {
+            assert_eq!(reloaded.get(&pos).unwrap(), schematic.get(&pos).unwrap(), "Mismatch at {:?}", pos);
+        }
+        assert!(vxl.read_next(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_schematic_paste_into() {
+        use crate::store::blockstore::{BlockStore, PagedBlockStore};
+
+        let stone = Rc::new(super::BlockState::from_str("minecraft:stone").unwrap());
+        let glass = Rc::new(super::BlockState::from_str("minecraft:glass").unwrap());
+        let mut schematic = super::Schematic::new(super::Boundary::new_from_size(2, 1, 1));
+        schematic.set(&super::BlockPosition::new(0, 0, 0), glass.clone()).unwrap();
+        schematic.set(&super::BlockPosition::new(1, 0, 0), super::BlockState::air_rc()).unwrap();
+
+        let target_boundary = super::Boundary::new_from_size(8, 8, 8);
+        let filled = || {
+            let mut target = PagedBlockStore::new_for_fixed_boundary(target_boundary);
+            for pos in target_boundary.iter(super::AxisOrder::XYZ) {
+                target.set_block_at(&pos, stone.clone()).unwrap();
+            }
+            target
+        };
+        let at = super::BlockPosition::new(3, 3, 3);
+
+        let mut target = filled();
+        schematic.paste_into(&mut target, at, true).unwrap();
+        assert_eq!(target.block_at(&super::BlockPosition::new(3, 3, 3)).unwrap(), Some(glass.clone()));
+        assert_eq!(target.block_at(&super::BlockPosition::new(4, 3, 3)).unwrap(), Some(stone.clone()));
+
+        let mut target = filled();
+        schematic.paste_into(&mut target, at, false).unwrap();
+        assert_eq!(target.block_at(&super::BlockPosition::new(3, 3, 3)).unwrap(), Some(glass.clone()));
+        assert!(target.block_at(&super::BlockPosition::new(4, 3, 3)).unwrap().unwrap().is_air());
+
+        let mut target = filled();
+        assert!(matches!(
+            schematic.paste_into(&mut target, super::BlockPosition::new(7, 0, 0), false),
+            Err(crate::stream::error::SchematicError::OutOfBounds(_))
+        ));
+        assert_eq!(target.block_at(&super::BlockPosition::new(7, 0, 0)).unwrap(), Some(stone.clone()));
+
+        let mut resizable = PagedBlockStore::new_empty_resizable();
+        schematic.paste_into(&mut resizable, super::BlockPosition::new(-5, 2, 0), true).unwrap();
+        assert_eq!(resizable.block_at(&super::BlockPosition::new(-5, 2, 0)).unwrap(), Some(glass));
+        assert!(resizable.boundary().contains(&super::BlockPosition::new(-5, 2, 0)));
+    }
+
+    #[test]
+    fn test_block_state_registry_intern() {
+        let first = super::BlockStateRegistry::intern(
+            super::BlockState::from_str("minecraft:oak_log[axis=y]").unwrap(),
+        );
+        let second = super::BlockStateRegistry::intern(
+            super::BlockState::from_str("minecraft:oak_log[axis=y]").unwrap(),
+        );
+        let other = super::BlockStateRegistry::intern(
+            super::BlockState::from_str("minecraft:oak_log[axis=x]").unwrap(),
+        );
+        assert!(Rc::ptr_eq(&first, &second));
+        assert!(!Rc::ptr_eq(&first, &other));
+    }
 
     #[test]
     fn test_block_state_parsing() {
@@ -907,11 +1644,11 @@ mod tests {
         assert_eq!(block_state.properties.len(), 2);
         assert_eq!(
             block_state.properties[0],
-            ("variant".to_string(), "granite".to_string())
+            ("hardness".to_string(), "1".to_string())
         );
         assert_eq!(
             block_state.properties[1],
-            ("hardness".to_string(), "1".to_string())
+            ("variant".to_string(), "granite".to_string())
         );
     }
 
@@ -942,11 +1679,11 @@ mod tests {
         assert_eq!(updated_state.properties.len(), 2);
         assert_eq!(
             updated_state.properties[0],
-            ("variant".to_string(), "diorite".to_string())
+            ("richy".to_string(), "nice".to_string())
         );
         assert_eq!(
             updated_state.properties[1],
-            ("richy".to_string(), "nice".to_string())
+            ("variant".to_string(), "diorite".to_string())
         );
         let other_updated_state = state1
             .update("-variant+fish=false,muffin=true".to_string())
@@ -995,6 +1732,29 @@ mod tests {
         assert_eq!(updated_state3.name, "minecraft:stone");
     }
 
+    #[test]
+    fn test_namespace_and_path() {
+        let modded = super::BlockState::new("create:cogwheel".to_string(), vec![("axis".to_string(), "y".to_string())]);
+        assert_eq!(modded.namespace(), "create");
+        assert_eq!(modded.path(), "cogwheel");
+        let vanilla = super::BlockState::from_str("minecraft:oak_log").unwrap();
+        assert_eq!((vanilla.namespace(), vanilla.path()), ("minecraft", "oak_log"));
+        let bare = super::BlockState::new("stone".to_string(), Vec::new());
+        assert_eq!((bare.namespace(), bare.path()), ("minecraft", "stone"));
+    }
+
+    #[test]
+    fn test_block_state_matches() {
+        let log = super::BlockState::from_str("minecraft:oak_log[axis=x]").unwrap();
+        assert!(log.matches("minecraft:oak_log"));
+        assert!(log.matches("minecraft:oak_log[axis=x]"));
+        assert!(!log.matches("minecraft:oak_log[axis=y]"));
+        assert!(!log.matches("minecraft:birch_log"));
+        assert!(!log.matches("minecraft:oak_log[axis=x,waterlogged=false]"));
+        let stairs = super::BlockState::from_str("minecraft:oak_stairs[half=top,facing=north]").unwrap();
+        assert!(stairs.matches("minecraft:oak_stairs[facing=north,half=top]"));
+    }
+
     #[test]
     fn test_indexing() {
         let boundary = super::Boundary::new(0, 0, 0, 4, 4, 4);
@@ -1005,6 +1765,26 @@ mod tests {
         assert_eq!(index_yzx, 1 * 16 + 3 * 4 + 2); // 1*16 + 3*4 + 2 = 30
     }
 
+    #[test]
+    fn test_indexing_beyond_i32() {
+        let boundary = super::Boundary::new(-1024, 0, 0, 2048, 1024, 1024);
+        assert!(boundary.volume() > i32::MAX as usize);
+        let positions = [
+            super::BlockPosition::new(-1024, 0, 0),
+            super::BlockPosition::new(0, 0, 0),
+            super::BlockPosition::new(0, 1023, 1023),
+            super::BlockPosition::new(1, 0, 0),
+            super::BlockPosition::new(1023, 1023, 1023),
+        ];
+        let indices: Vec<i64> = positions.iter()
+            .map(|pos| super::AxisOrder::XYZ.index(pos, &boundary))
+            .collect();
+        assert_eq!(indices[0], 0);
+        assert_eq!(indices[1], 1024 * 1024 * 1024);
+        assert!(indices.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(indices[4], boundary.volume() as i64 - 1);
+    }
+
     #[test]
     fn test_boundary_iterator() {
         let boundary = super::Boundary::new(0, 0, 0, 2, 2, 2);
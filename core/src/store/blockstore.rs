@@ -1,16 +1,45 @@
 use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
 use crate::store::paging::{ArrayPage, Page};
 use rustc_hash::FxHashMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 pub trait BlockStore: Region {
     fn block_at(&self, pos: &BlockPosition) -> Result<Option<Rc<BlockState>>, String>;
     fn set_block_at(&mut self, pos: &BlockPosition, state: Rc<BlockState>) -> Result<(), String>;
+    /// Sets the block at `pos` and returns the state that was previously stored there, if any.
+    fn set_block_at_replacing(
+        &mut self,
+        pos: &BlockPosition,
+        state: Rc<BlockState>,
+    ) -> Result<Option<Rc<BlockState>>, String>;
     fn remove_block_at(&mut self, pos: BlockPosition) -> Result<(), String>;
     fn boundary(&self) -> &Boundary;
     fn set_boundary(&mut self, boundary: Boundary);
     fn resizable(&self) -> bool;
+    /// Switches between growing to fit new blocks and rejecting positions outside the boundary.
+    /// Required because the flag lives in the store; `freeze_boundary` relies on it taking effect.
+    fn set_resizable(&mut self, resizable: bool);
+    /// Removes every block and palette entry while keeping the boundary and resizability,
+    /// so the store can be refilled without reallocating its maps.
+    /// Erases the whole boundary by default, stores with their own maps clear those directly.
+    fn clear(&mut self) {
+        let boundary = *self.boundary();
+        // `remove_region` only visits positions inside the boundary, which no store rejects
+        let _ = self.remove_region(&boundary);
+        self.compact_palette();
+    }
+
+    /// Shrinks a resizable store to the smallest box containing all its blocks and fixes its size.
+    /// Fixed stores are left untouched.
+    fn freeze_boundary(&mut self) {
+        if !self.resizable() {
+            return;
+        }
+        let boundary = Boundary::from_positions(self.block_iterator(AxisOrder::XYZ).map(|(pos, _)| pos));
+        self.set_boundary(boundary);
+        self.set_resizable(false);
+    }
 
     fn insert(&mut self, blocks: &[Block], offset: usize, length: usize) -> Result<(), String> {
         for i in 0..length {
@@ -27,13 +56,80 @@ pub trait BlockStore: Region {
         Box::new(
             self.iter(axis_order)
                 .map(move |pos| {
-                    let state = self.block_at(&pos).unwrap_or(None);
+                    // `iter` only yields positions inside the boundary, which `block_at` never rejects
+                    let state = self.block_at(&pos).ok().flatten();
                     (pos, state)
                 })
                 .filter(move |(_pos, state)| state.is_some() && !state.as_ref().unwrap().is_air()),
         )
     }
 
+    /// Whether any cell of the store holds `state`.
+    /// Scans all blocks by default, stores with a palette answer from it instead.
+    fn contains_state(&self, state: &BlockState) -> bool {
+        self.block_iterator(AxisOrder::XYZ)
+            .any(|(_, stored)| stored.is_some_and(|stored| stored.as_ref() == state))
+    }
+
+    /// Every distinct non-air state in the store, in no particular order.
+    /// Scans all blocks by default, stores with a palette answer from it instead.
+    fn distinct_states(&self) -> Vec<Rc<BlockState>> {
+        let mut seen = HashSet::new();
+        self.block_iterator(AxisOrder::XYZ)
+            .filter_map(|(_, state)| state)
+            .filter(|state| !state.is_air())
+            .filter(|state| seen.insert(state.clone()))
+            .collect()
+    }
+
+    /// Clears every block inside `region`, e.g. the interior of a box to leave a hollow shell.
+    /// Only the part of `region` overlapping the store's boundary is visited.
+    fn remove_region(&mut self, region: &Boundary) -> Result<(), String> {
+        let own = *self.boundary();
+        let min = BlockPosition::new(
+            own.min_x.max(region.min_x),
+            own.min_y.max(region.min_y),
+            own.min_z.max(region.min_z),
+        );
+        let max = BlockPosition::new(
+            (own.min_x + own.d_x).min(region.min_x + region.d_x) - 1,
+            (own.min_y + own.d_y).min(region.min_y + region.d_y) - 1,
+            (own.min_z + own.d_z).min(region.min_z + region.d_z) - 1,
+        );
+        if min.x() > max.x() || min.y() > max.y() || min.z() > max.z() {
+            return Ok(());
+        }
+        let intersection = Boundary::new_from_positions(&min, &max);
+        for pos in intersection.iter(AxisOrder::XYZ) {
+            // paged stores refuse to erase cells that are already empty
+            if self.block_at(&pos)?.is_some() {
+                self.remove_block_at(pos)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops palette entries that no cell refers to anymore.
+    /// Stores without a persistent palette have nothing to compact.
+    fn compact_palette(&mut self) {}
+
+    /// Iterates every position of the boundary, yielding air for empty cells.
+    /// Unlike `block_iterator`, this is dense and suited for writers that need air as well.
+    fn iterate_all(
+        &self,
+        axis_order: AxisOrder,
+    ) -> Box<dyn Iterator<Item = (BlockPosition, Rc<BlockState>)> + '_> {
+        Box::new(self.iter(axis_order).map(move |pos| {
+            // `iter` only yields positions inside the boundary, which `block_at` never rejects
+            let state = self
+                .block_at(&pos)
+                .ok()
+                .flatten()
+                .unwrap_or_else(BlockState::air_rc);
+            (pos, state)
+        }))
+    }
+
     fn _expand_or_throw(&mut self, pos: &BlockPosition) -> Result<(), String> {
         let contains = self.boundary().contains(&pos);
         if !self.resizable() && !contains {
@@ -109,12 +205,29 @@ impl BlockStore for SparseBlockStore {
         Ok(())
     }
 
+    fn set_block_at_replacing(
+        &mut self,
+        pos: &BlockPosition,
+        state: Rc<BlockState>,
+    ) -> Result<Option<Rc<BlockState>>, String> {
+        self._expand_or_throw(pos)?;
+        let index = self.get_or_add_palette_index(state);
+        let previous = self.data.insert(*pos, index);
+        Ok(previous.and_then(|i| self.palette.get(i).cloned()))
+    }
+
     fn remove_block_at(&mut self, pos: BlockPosition) -> Result<(), String> {
         self._expand_or_throw(&pos)?;
         self.data.remove(&pos);
         Ok(())
     }
 
+    fn clear(&mut self) {
+        self.data.clear();
+        self.palette.clear();
+        self.reverse_palette.clear();
+    }
+
     fn boundary(&self) -> &Boundary {
         &self.boundary
     }
@@ -126,6 +239,26 @@ impl BlockStore for SparseBlockStore {
     fn resizable(&self) -> bool {
         !self.fixed_size
     }
+
+    fn set_resizable(&mut self, resizable: bool) {
+        self.fixed_size = !resizable;
+    }
+
+    fn contains_state(&self, state: &BlockState) -> bool {
+        match self.reverse_palette.get(state) {
+            Some(&index) => self.data.values().any(|&stored| stored == index),
+            None => false,
+        }
+    }
+
+    fn distinct_states(&self) -> Vec<Rc<BlockState>> {
+        let used: HashSet<usize> = self.data.values().copied().collect();
+        self.palette.iter()
+            .enumerate()
+            .filter(|(index, state)| used.contains(index) && !state.is_air())
+            .map(|(_, state)| state.clone())
+            .collect()
+    }
 }
 
 pub struct PagedBlockStore {
@@ -141,51 +274,108 @@ pub struct PagedBlockStore {
     mask_x: u32,
     mask_y: u32,
     mask_z: u32,
+    page_axis_order: AxisOrder,
+    origin: BlockPosition,
     boundary: Boundary,
     fixed_size: bool,
 }
 
-impl PagedBlockStore {
-    pub fn new_empty_resizable() -> Self {
-        PagedBlockStore::new(
-            Boundary::new(0, 0, 0, 0, 0, 0),
-            16,
-            16,
-            16,
-            false,
-        )
+/// Pages allocated up front when no capacity hint is given.
+const DEFAULT_PAGE_CAPACITY: usize = 1024;
+/// Upper bound for hinted preallocation, so a huge declared volume cannot reserve memory on its own.
+const MAX_PREALLOCATED_PAGES: usize = 1 << 16;
+
+/// A decoded `PagedBlockStore` page key: the page's index along each axis,
+/// counted in pages from the store's origin (negative below it).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageKey {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl PageKey {
+    /// Splits a packed key back into its three signed 20-bit page indices.
+    pub fn decode(key: i64) -> PageKey {
+        let index = |shift: u32| ((((key >> shift) & 0xFFFFF) as i32) << 12) >> 12;
+        PageKey {
+            x: index(40),
+            y: index(20),
+            z: index(0),
+        }
     }
+}
 
-    pub fn new_for_fixed_boundary(boundary: Boundary) -> Self {
-        PagedBlockStore::new_for_boundary(boundary, true)
+/// Configures a `PagedBlockStore`. Defaults to an empty resizable boundary, page sizes derived
+/// from the boundary and the preferred page axis order.
+pub struct PagedBlockStoreBuilder {
+    boundary: Boundary,
+    page_size: Option<(usize, usize, usize)>,
+    fixed: bool,
+    axis_order: AxisOrder,
+    capacity_hint: Option<usize>,
+}
+
+impl PagedBlockStoreBuilder {
+    pub fn boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
     }
 
-    pub fn new_for_boundary(boundary: Boundary, fixed_size: bool) -> Self {
-        let page_size_x = ((boundary.d_x() / 8) as usize).clamp(8, 64);
-        let page_size_y = ((boundary.d_y() / 8) as usize).clamp(8, 64);
-        let page_size_z = ((boundary.d_z() / 8) as usize).clamp(8, 64);
-        PagedBlockStore::new(boundary, page_size_x, page_size_y, page_size_z, fixed_size)
+    /// Requested page dimensions, each rounded up to a power of two.
+    pub fn page_size(mut self, x: usize, y: usize, z: usize) -> Self {
+        self.page_size = Some((x, y, z));
+        self
     }
 
-    pub fn new(
-        boundary: Boundary,
-        req_page_size_x: usize,
-        req_page_size_y: usize,
-        req_page_size_z: usize,
-        fixed_size: bool,
-    ) -> Self {
-        let bits_x = (Self::round_to_power_of_two(req_page_size_x) as u32).trailing_zeros();
-        let bits_y = (Self::round_to_power_of_two(req_page_size_y) as u32).trailing_zeros();
-        let bits_z = (Self::round_to_power_of_two(req_page_size_z) as u32).trailing_zeros();
+    pub fn fixed(mut self, fixed: bool) -> Self {
+        self.fixed = fixed;
+        self
+    }
+
+    /// Memory layout inside each page. Matching it to the order the store is mostly filled
+    /// and read in keeps neighbouring cells close together.
+    pub fn axis_order(mut self, axis_order: AxisOrder) -> Self {
+        self.axis_order = axis_order;
+        self
+    }
+
+    /// Number of blocks the store is expected to hold, e.g. a reader's declared volume.
+    /// The page map is allocated for them up front instead of growing while it is filled.
+    pub fn capacity_hint(mut self, blocks: usize) -> Self {
+        self.capacity_hint = Some(blocks);
+        self
+    }
+
+    pub fn build(self) -> PagedBlockStore {
+        let boundary = self.boundary;
+        let (req_page_size_x, req_page_size_y, req_page_size_z) = self.page_size.unwrap_or_else(|| (
+            ((boundary.d_x() / 8) as usize).clamp(8, 64),
+            ((boundary.d_y() / 8) as usize).clamp(8, 64),
+            ((boundary.d_z() / 8) as usize).clamp(8, 64),
+        ));
+        let bits_x = (PagedBlockStore::round_to_power_of_two(req_page_size_x) as u32).trailing_zeros();
+        let bits_y = (PagedBlockStore::round_to_power_of_two(req_page_size_y) as u32).trailing_zeros();
+        let bits_z = (PagedBlockStore::round_to_power_of_two(req_page_size_z) as u32).trailing_zeros();
         let mask_x = (1u32 << bits_x) - 1;
         let mask_y = (1u32 << bits_y) - 1;
         let mask_z = (1u32 << bits_z) - 1;
         let page_size_x = 1usize << bits_x;
         let page_size_y = 1usize << bits_y;
         let page_size_z = 1usize << bits_z;
+        let page_capacity = match self.capacity_hint {
+            // a hint covering the whole boundary needs every page it overlaps, including partial ones at the edges
+            Some(blocks) if blocks >= boundary.volume() => {
+                (boundary.d_x().max(0) as usize).div_ceil(page_size_x)
+                    * (boundary.d_y().max(0) as usize).div_ceil(page_size_y)
+                    * (boundary.d_z().max(0) as usize).div_ceil(page_size_z)
+            }
+            Some(blocks) => blocks.div_ceil(page_size_x * page_size_y * page_size_z),
+            None => DEFAULT_PAGE_CAPACITY,
+        };
 
         PagedBlockStore {
-            pages: FxHashMap::with_capacity_and_hasher(1024, Default::default()),
+            pages: FxHashMap::with_capacity_and_hasher(page_capacity.min(MAX_PREALLOCATED_PAGES), Default::default()),
             palette: Vec::new(),
             reverse_palette: HashMap::new(),
             page_size_x,
@@ -197,11 +387,81 @@ impl PagedBlockStore {
             mask_x,
             mask_y,
             mask_z,
+            page_axis_order: self.axis_order,
+            origin: BlockPosition::new(boundary.min_x, boundary.min_y, boundary.min_z),
             boundary,
-            fixed_size,
+            fixed_size: self.fixed,
+        }
+    }
+}
+
+impl PagedBlockStore {
+    pub fn builder() -> PagedBlockStoreBuilder {
+        PagedBlockStoreBuilder {
+            boundary: Boundary::new_empty(),
+            page_size: None,
+            fixed: false,
+            axis_order: AxisOrder::preferred(),
+            capacity_hint: None,
         }
     }
 
+    pub fn new_empty_resizable() -> Self {
+        PagedBlockStore::new(Boundary::new_empty(), 16, 16, 16, false, AxisOrder::preferred())
+    }
+
+    pub fn new_for_fixed_boundary(boundary: Boundary) -> Self {
+        PagedBlockStore::new_for_boundary(boundary, true)
+    }
+
+    pub fn new_for_boundary(boundary: Boundary, fixed_size: bool) -> Self {
+        PagedBlockStore::builder().boundary(boundary).fixed(fixed_size).build()
+    }
+
+    /// `page_axis_order` is the memory layout inside each page, see `PagedBlockStoreBuilder::axis_order`.
+    pub fn new(
+        boundary: Boundary,
+        req_page_size_x: usize,
+        req_page_size_y: usize,
+        req_page_size_z: usize,
+        fixed_size: bool,
+        page_axis_order: AxisOrder,
+    ) -> Self {
+        PagedBlockStore::builder()
+            .boundary(boundary)
+            .page_size(req_page_size_x, req_page_size_y, req_page_size_z)
+            .fixed(fixed_size)
+            .axis_order(page_axis_order)
+            .build()
+    }
+
+    /// Splits a position into its page key and the local coordinates within that page.
+    /// Positions are taken relative to the initial boundary min, and pages are indexed
+    /// signed so that negative (or, after expansion, below-origin) coordinates work.
+    fn page_key_and_local(&self, pos: &BlockPosition) -> (i64, i32, i32, i32) {
+        let rel_x = pos.x().wrapping_sub(self.origin.x());
+        let rel_y = pos.y().wrapping_sub(self.origin.y());
+        let rel_z = pos.z().wrapping_sub(self.origin.z());
+        let page_x = (rel_x >> self.bits_x) as i64 & 0xFFFFF;
+        let page_y = (rel_y >> self.bits_y) as i64 & 0xFFFFF;
+        let page_z = (rel_z >> self.bits_z) as i64 & 0xFFFFF;
+        let page_key = (page_x << 40) | (page_y << 20) | page_z;
+        (
+            page_key,
+            (rel_x as u32 & self.mask_x) as i32,
+            (rel_y as u32 & self.mask_y) as i32,
+            (rel_z as u32 & self.mask_z) as i32,
+        )
+    }
+
+    /// Every page holding at least one block, in no particular order. Lets callers work
+    /// page by page instead of visiting every position of the boundary.
+    pub fn iter_pages(&self) -> impl Iterator<Item = (PageKey, &dyn Page)> + '_ {
+        self.pages.iter()
+            .filter(|(_, page)| !page.is_empty())
+            .map(|(&key, page)| (PageKey::decode(key), page.as_ref()))
+    }
+
     fn get_or_add_palette_index(&mut self, state: Rc<BlockState>) -> u16 {
         if let Some(&index) = self.reverse_palette.get(state.as_ref()) {
             index
@@ -237,15 +497,9 @@ impl BlockStore for PagedBlockStore {
         if !self.boundary().contains(&pos) {
             return Err("Position out of bounds".to_string());
         }
-        let page_x = (pos.x() as u32) >> self.bits_x;
-        let page_y = (pos.y() as u32) >> self.bits_y;
-        let page_z = (pos.z() as u32) >> self.bits_z;
-        let page_key = ((page_x as i64) << 40) | ((page_y as i64) << 20) | (page_z as i64);
+        let (page_key, local_x, local_y, local_z) = self.page_key_and_local(pos);
         if let Some(page) = self.pages.get(&page_key) {
-            let local_x = (pos.x() as u32) & self.mask_x;
-            let local_y = (pos.y() as u32) & self.mask_y;
-            let local_z = (pos.z() as u32) & self.mask_z;
-            match page.load(local_x as i32, local_y as i32, local_z as i32) {
+            match page.load(local_x, local_y, local_z) {
                 Some(index) => Ok(self.palette.get(index as usize).cloned()),
                 None => Ok(None),
             }
@@ -256,41 +510,56 @@ impl BlockStore for PagedBlockStore {
 
     fn set_block_at(&mut self, pos: &BlockPosition, state: Rc<BlockState>) -> Result<(), String> {
         self._expand_or_throw(&pos)?;
-        let page_x = (pos.x() as u32) >> self.bits_x;
-        let page_y = (pos.y() as u32) >> self.bits_y;
-        let page_z = (pos.z() as u32) >> self.bits_z;
-        let page_key = ((page_x as i64) << 40) | ((page_y as i64) << 20) | (page_z as i64);
+        let (page_key, local_x, local_y, local_z) = self.page_key_and_local(pos);
         let index = self.get_or_add_palette_index(state);
         let page = self.pages.entry(page_key).or_insert_with(|| {
             Box::new(ArrayPage::new(
                 self.page_size_x,
                 self.page_size_y,
                 self.page_size_z,
-                AxisOrder::preferred(),
+                self.page_axis_order,
             ))
         });
-        let local_x = (pos.x() as u32) & self.mask_x;
-        let local_y = (pos.y() as u32) & self.mask_y;
-        let local_z = (pos.z() as u32) & self.mask_z;
-        page.store(local_x as i32, local_y as i32, local_z as i32, index)?;
+        page.store(local_x, local_y, local_z, index)?;
         Ok(())
     }
 
+    fn set_block_at_replacing(
+        &mut self,
+        pos: &BlockPosition,
+        state: Rc<BlockState>,
+    ) -> Result<Option<Rc<BlockState>>, String> {
+        self._expand_or_throw(pos)?;
+        let (page_key, local_x, local_y, local_z) = self.page_key_and_local(pos);
+        let index = self.get_or_add_palette_index(state);
+        let page = self.pages.entry(page_key).or_insert_with(|| {
+            Box::new(ArrayPage::new(
+                self.page_size_x,
+                self.page_size_y,
+                self.page_size_z,
+                self.page_axis_order,
+            ))
+        });
+        let previous = page.load(local_x, local_y, local_z);
+        page.store(local_x, local_y, local_z, index)?;
+        Ok(previous.and_then(|i| self.palette.get(i as usize).cloned()))
+    }
+
     fn remove_block_at(&mut self, pos: BlockPosition) -> Result<(), String> {
         self._expand_or_throw(&pos)?;
-        let page_x = (pos.x() as u32) >> self.bits_x;
-        let page_y = (pos.y() as u32) >> self.bits_y;
-        let page_z = (pos.z() as u32) >> self.bits_z;
-        let page_key = ((page_x as i64) << 40) | ((page_y as i64) << 20) | (page_z as i64);
+        let (page_key, local_x, local_y, local_z) = self.page_key_and_local(&pos);
         if let Some(page) = self.pages.get_mut(&page_key) {
-            let local_x = (pos.x() as u32) & self.mask_x;
-            let local_y = (pos.y() as u32) & self.mask_y;
-            let local_z = (pos.z() as u32) & self.mask_z;
-            page.erase(local_x as i32, local_y as i32, local_z as i32)?;
+            page.erase(local_x, local_y, local_z)?;
         }
         Ok(())
     }
 
+    fn clear(&mut self) {
+        self.pages.clear();
+        self.palette.clear();
+        self.reverse_palette.clear();
+    }
+
     fn boundary(&self) -> &Boundary {
         &self.boundary
     }
@@ -302,6 +571,50 @@ impl BlockStore for PagedBlockStore {
     fn resizable(&self) -> bool {
         !self.fixed_size
     }
+
+    fn set_resizable(&mut self, resizable: bool) {
+        self.fixed_size = !resizable;
+    }
+
+    /// Drops palette entries no cell refers to anymore and renumbers the rest densely,
+    /// rewriting every page. Useful after many removals or overwrites.
+    fn compact_palette(&mut self) {
+        let mut used = vec![false; self.palette.len()];
+        for (_, page) in self.iter_pages() {
+            page.for_each_state(&mut |index| used[index as usize] = true);
+        }
+        let mut mapping = vec![0u16; self.palette.len()];
+        let mut palette = Vec::new();
+        for (index, state) in self.palette.drain(..).enumerate() {
+            if used[index] {
+                mapping[index] = palette.len() as u16;
+                palette.push(state);
+            }
+        }
+        for page in self.pages.values_mut() {
+            page.remap_states(&mapping);
+        }
+        self.reverse_palette = palette.iter()
+            .enumerate()
+            .map(|(index, state)| (state.clone(), index as u16))
+            .collect();
+        self.palette = palette;
+    }
+
+    /// Answers from the palette in O(1). Palette entries are only dropped by `compact_palette`,
+    /// so until then a state whose cells were all removed or overwritten is still reported as contained.
+    fn contains_state(&self, state: &BlockState) -> bool {
+        self.reverse_palette.contains_key(state)
+    }
+
+    /// Returns the palette, with the same caveat as `contains_state`: states whose cells were
+    /// all removed or overwritten are still listed.
+    fn distinct_states(&self) -> Vec<Rc<BlockState>> {
+        self.palette.iter()
+            .filter(|state| !state.is_air())
+            .cloned()
+            .collect()
+    }
 }
 
 pub struct LazyPaletteBlockStoreWrapper {
@@ -323,6 +636,13 @@ fn temp_state_from_temp_id(
         }).clone()
 }
 
+fn temp_id_from_state(state: &BlockState) -> Option<isize> {
+    if !state.properties().is_empty() {
+        return None;
+    }
+    state.name_ref().parse().ok()
+}
+
 impl Region for LazyPaletteBlockStoreWrapper {
     fn contains(&self, pos: &BlockPosition) -> bool {
         self.inner.contains(pos)
@@ -341,9 +661,12 @@ impl LazyPaletteBlockStoreWrapper {
         )))
     }
 
+    /// Fixed stores are filled up to their full size, so the page map is preallocated for it.
     pub fn empty_fixed_from_size(size_x: usize, size_y: usize, size_z: usize) -> Self {
         let boundary = Boundary::new(0, 0, 0, size_x as i32, size_y as i32, size_z as i32);
-        LazyPaletteBlockStoreWrapper::from(Box::new(PagedBlockStore::new_for_boundary(boundary, true)))
+        LazyPaletteBlockStoreWrapper::from(Box::new(
+            PagedBlockStore::builder().boundary(boundary).fixed(true).capacity_hint(boundary.volume()).build(),
+        ))
     }
 
     pub fn from(inner: Box<dyn BlockStore>) -> Self {
@@ -358,10 +681,11 @@ impl LazyPaletteBlockStoreWrapper {
         match self.actual_palette {
             Some(ref palette) => {
                 if let Some(state) = self.inner.block_at(pos)? {
-                    let id_str = state.name_ref();
-                    let id: isize = id_str
-                        .parse()
-                        .map_err(|_| "Invalid temporary ID".to_string())?;
+                    // real states can end up in the inner store, hand them out untouched
+                    let id = match temp_id_from_state(&state) {
+                        Some(id) => id,
+                        None => return Ok(Some(state)),
+                    };
                     if let Some(actual_state) = palette.get(&id) {
                         Ok(Some(actual_state.clone()))
                     } else {
@@ -421,6 +745,16 @@ impl LazyPaletteBlockStoreWrapper {
     pub fn set_actual_palette(&mut self, palette: HashMap<isize, Rc<BlockState>>) {
         self.actual_palette = Some(palette);
     }
+
+    /// Whether the actual palette declares `id`, false if none has been provided yet.
+    pub fn has_palette_id(&self, id: isize) -> bool {
+        self.actual_palette.as_ref().is_some_and(|palette| palette.contains_key(&id))
+    }
+
+    /// Number of entries in the actual palette, zero if none has been provided yet.
+    pub fn palette_len(&self) -> usize {
+        self.actual_palette.as_ref().map_or(0, |palette| palette.len())
+    }
 }
 
 // testing time
@@ -508,6 +842,328 @@ mod tests {
         assert!(retrieved.is_none());
     }
 
+    #[test]
+    fn test_paged_block_store_negative_coordinates() {
+        let boundary = Boundary::new(-100, 0, -100, 200, 16, 200);
+        let mut store = PagedBlockStore::new_for_fixed_boundary(boundary);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        store.set_block_at(&BlockPosition::new(-100, 0, -100), stone.clone()).unwrap();
+        store.set_block_at(&BlockPosition::new(-1, 5, -1), dirt.clone()).unwrap();
+        store.set_block_at(&BlockPosition::new(99, 15, 99), stone.clone()).unwrap();
+        assert_eq!(store.block_at(&BlockPosition::new(-100, 0, -100)).unwrap(), Some(stone.clone()));
+        assert_eq!(store.block_at(&BlockPosition::new(-1, 5, -1)).unwrap(), Some(dirt));
+        assert_eq!(store.block_at(&BlockPosition::new(99, 15, 99)).unwrap(), Some(stone));
+        assert_eq!(store.block_at(&BlockPosition::new(0, 5, 0)).unwrap(), None);
+
+        let mut resizable = PagedBlockStore::new_empty_resizable();
+        let granite = Rc::new(BlockState::from_str("minecraft:granite").unwrap());
+        resizable.set_block_at(&BlockPosition::new(-20, -3, -7), granite.clone()).unwrap();
+        assert_eq!(resizable.block_at(&BlockPosition::new(-20, -3, -7)).unwrap(), Some(granite));
+        assert_eq!(resizable.block_at(&BlockPosition::new(-19, -3, -7)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_freeze_boundary_is_minimal() {
+        let mut store = PagedBlockStore::new_empty_resizable();
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        for pos in [
+            BlockPosition::new(3, 10, -2),
+            BlockPosition::new(-4, 12, 5),
+            BlockPosition::new(7, 11, 0),
+        ] {
+            store.set_block_at(&pos, stone.clone()).unwrap();
+        }
+        store.freeze_boundary();
+        assert!(!store.resizable());
+        assert_eq!(*store.boundary(), Boundary::new_from_min_max(-4, 10, -2, 7, 12, 5));
+        assert!(store.set_block_at(&BlockPosition::new(8, 10, 0), stone.clone()).is_err());
+        assert_eq!(store.block_iterator(AxisOrder::XYZ).count(), 3);
+    }
+
+    #[test]
+    fn test_set_block_at_replacing_sparse() {
+        let boundary = Boundary::new(0, 0, 0, 4, 4, 4);
+        let mut store = SparseBlockStore::new(boundary, true);
+        let pos = BlockPosition::new(1, 2, 3);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        assert_eq!(store.set_block_at_replacing(&pos, stone.clone()).unwrap(), None);
+        assert_eq!(store.set_block_at_replacing(&pos, dirt.clone()).unwrap(), Some(stone));
+        assert_eq!(store.block_at(&pos).unwrap(), Some(dirt));
+    }
+
+    #[test]
+    fn test_set_block_at_replacing_paged() {
+        let boundary = Boundary::new(0, 0, 0, 32, 32, 32);
+        let mut store = PagedBlockStore::new_for_boundary(boundary, true);
+        let pos = BlockPosition::new(17, 3, 9);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        assert_eq!(store.set_block_at_replacing(&pos, stone.clone()).unwrap(), None);
+        assert_eq!(store.set_block_at_replacing(&pos, dirt.clone()).unwrap(), Some(stone.clone()));
+        assert_eq!(store.set_block_at_replacing(&pos, dirt.clone()).unwrap(), Some(dirt.clone()));
+        store.remove_block_at(pos).unwrap();
+        assert_eq!(store.set_block_at_replacing(&pos, stone).unwrap(), None);
+        assert!(store.set_block_at_replacing(&BlockPosition::new(32, 0, 0), dirt).is_err());
+    }
+
+    #[test]
+    fn test_iterate_all_includes_air() {
+        let boundary = Boundary::new(0, 0, 0, 4, 4, 4);
+        let mut store = PagedBlockStore::new_for_fixed_boundary(boundary);
+        assert_eq!(store.iterate_all(AxisOrder::XYZ).count(), 64);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        store.set_block_at(&BlockPosition::new(1, 1, 1), stone.clone()).unwrap();
+        store.set_block_at(&BlockPosition::new(3, 0, 2), stone.clone()).unwrap();
+        let all: Vec<_> = store.iterate_all(AxisOrder::YZX).collect();
+        assert_eq!(all.len(), 64);
+        assert_eq!(all.iter().filter(|(_, state)| state.is_air()).count(), 62);
+        assert_eq!(store.block_iterator(AxisOrder::XYZ).count(), 2);
+    }
+
+    #[test]
+    fn test_contains_state() {
+        let boundary = Boundary::new(0, 0, 0, 8, 8, 8);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let tnt = BlockState::from_str("minecraft:tnt").unwrap();
+        let mut paged = PagedBlockStore::new_for_fixed_boundary(boundary);
+        let mut sparse = SparseBlockStore::new(boundary, true);
+        for store in [&mut paged as &mut dyn BlockStore, &mut sparse] {
+            store.set_block_at(&BlockPosition::new(2, 3, 4), stone.clone()).unwrap();
+            assert!(store.contains_state(&stone));
+            assert!(!store.contains_state(&tnt));
+            store.set_block_at(&BlockPosition::new(7, 7, 7), Rc::new(tnt.clone())).unwrap();
+            assert!(store.contains_state(&tnt));
+        }
+        sparse.set_block_at(&BlockPosition::new(7, 7, 7), stone.clone()).unwrap();
+        assert!(!sparse.contains_state(&tnt));
+    }
+
+    #[test]
+    fn test_distinct_states() {
+        let boundary = Boundary::new(0, 0, 0, 8, 8, 8);
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:oak_log[axis=y]"];
+        let states: Vec<Rc<BlockState>> = names.iter()
+            .map(|name| Rc::new(BlockState::from_str(name).unwrap()))
+            .collect();
+        let mut paged = PagedBlockStore::new_for_fixed_boundary(boundary);
+        let mut sparse = SparseBlockStore::new(boundary, true);
+        for store in [&mut paged as &mut dyn BlockStore, &mut sparse] {
+            for (i, pos) in boundary.iter(AxisOrder::XYZ).take(30).enumerate() {
+                store.set_block_at(&pos, states[i % 3].clone()).unwrap();
+            }
+            let mut distinct: Vec<String> = store.distinct_states().iter().map(|s| s.to_string()).collect();
+            distinct.sort();
+            assert_eq!(distinct, vec!["minecraft:dirt", "minecraft:oak_log[axis=y]", "minecraft:stone"]);
+        }
+    }
+
+    #[test]
+    fn test_builder_matches_constructor() {
+        let boundary = Boundary::new(-4, 0, 7, 40, 12, 9);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let mut built = PagedBlockStore::builder()
+            .boundary(boundary)
+            .page_size(16, 8, 8)
+            .fixed(true)
+            .axis_order(AxisOrder::YZX)
+            .build();
+        let mut constructed = PagedBlockStore::new(boundary, 16, 8, 8, true, AxisOrder::YZX);
+        for store in [&mut built, &mut constructed] {
+            for pos in boundary.iter(AxisOrder::XYZ).step_by(3) {
+                store.set_block_at(&pos, stone.clone()).unwrap();
+            }
+            assert!(store.set_block_at(&BlockPosition::new(100, 0, 0), stone.clone()).is_err());
+        }
+        assert_eq!(built.boundary(), constructed.boundary());
+        assert_eq!(built.resizable(), constructed.resizable());
+        assert_eq!((built.page_size_x, built.page_size_y, built.page_size_z), (16, 8, 8));
+        let built_blocks: Vec<_> = built.block_iterator(AxisOrder::XYZ).collect();
+        let constructed_blocks: Vec<_> = constructed.block_iterator(AxisOrder::XYZ).collect();
+        assert_eq!(built_blocks, constructed_blocks);
+
+        let resizable = PagedBlockStore::builder().build();
+        assert!(resizable.resizable());
+        assert_eq!(resizable.boundary(), &Boundary::new_empty());
+    }
+
+    #[test]
+    fn test_yzx_paged_store() {
+        let boundary = Boundary::new(-3, 2, 5, 20, 11, 17);
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:glass", "minecraft:sand", "minecraft:tnt"];
+        let states: Vec<Rc<BlockState>> = names.iter()
+            .map(|name| Rc::new(BlockState::from_str(name).unwrap()))
+            .collect();
+        let mut xyz = PagedBlockStore::new(boundary, 8, 8, 8, true, AxisOrder::XYZ);
+        let mut yzx = PagedBlockStore::new(boundary, 8, 8, 8, true, AxisOrder::YZX);
+        for (i, pos) in boundary.iter(AxisOrder::YZX).enumerate() {
+            if i % 7 != 0 {
+                xyz.set_block_at(&pos, states[i % states.len()].clone()).unwrap();
+                yzx.set_block_at(&pos, states[i % states.len()].clone()).unwrap();
+            }
+        }
+        for order in [AxisOrder::XYZ, AxisOrder::YZX, AxisOrder::ZYX] {
+            let expected: Vec<_> = xyz.block_iterator(order).collect();
+            let actual: Vec<_> = yzx.block_iterator(order).collect();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_compact_palette() {
+        let boundary = Boundary::new(0, 0, 0, 20, 4, 4);
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:glass", "minecraft:sand"];
+        let states: Vec<Rc<BlockState>> = names.iter()
+            .map(|name| Rc::new(BlockState::from_str(name).unwrap()))
+            .collect();
+        let mut store = PagedBlockStore::new(boundary, 8, 8, 8, true, AxisOrder::XYZ);
+        for (i, pos) in boundary.iter(AxisOrder::XYZ).enumerate() {
+            store.set_block_at(&pos, states[i % states.len()].clone()).unwrap();
+        }
+        // drop every dirt and glass cell, overwriting some and removing the rest
+        for (i, pos) in boundary.iter(AxisOrder::XYZ).enumerate() {
+            match i % states.len() {
+                1 => store.remove_block_at(pos).unwrap(),
+                2 => store.set_block_at(&pos, states[3].clone()).unwrap(),
+                _ => {}
+            }
+        }
+        let expected: Vec<_> = store.block_iterator(AxisOrder::XYZ).collect();
+        assert_eq!(store.palette.len(), 4);
+        assert!(store.contains_state(&states[1]));
+
+        store.compact_palette();
+        assert_eq!(store.palette.len(), 2);
+        assert_eq!(store.reverse_palette.len(), 2);
+        assert!(!store.contains_state(&states[1]));
+        assert!(!store.contains_state(&states[2]));
+        assert_eq!(store.block_iterator(AxisOrder::XYZ).collect::<Vec<_>>(), expected);
+
+        store.set_block_at(&BlockPosition::new(0, 0, 0), states[2].clone()).unwrap();
+        assert_eq!(store.block_at(&BlockPosition::new(0, 0, 0)).unwrap(), Some(states[2].clone()));
+        let sand_pos = boundary.iter(AxisOrder::XYZ).nth(3).unwrap();
+        assert_eq!(store.block_at(&sand_pos).unwrap(), Some(states[3].clone()));
+    }
+
+    #[test]
+    fn test_remove_region() {
+        let boundary = Boundary::new(0, 0, 0, 4, 4, 4);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let inner = Boundary::new(1, 1, 1, 2, 2, 2);
+        let mut paged = PagedBlockStore::new_for_fixed_boundary(boundary);
+        let mut sparse = SparseBlockStore::new(boundary, true);
+        for store in [&mut paged as &mut dyn BlockStore, &mut sparse] {
+            for pos in boundary.iter(AxisOrder::XYZ) {
+                store.set_block_at(&pos, stone.clone()).unwrap();
+            }
+            store.remove_region(&inner).unwrap();
+            for pos in boundary.iter(AxisOrder::XYZ) {
+                let state = store.block_at(&pos).unwrap();
+                assert_eq!(state.is_none(), inner.contains(&pos), "{:?}", pos);
+            }
+            assert_eq!(store.block_iterator(AxisOrder::XYZ).count(), 64 - 8);
+
+            // regions reaching past the boundary are clipped instead of failing
+            store.remove_region(&Boundary::new(3, -2, -2, 10, 10, 10)).unwrap();
+            assert_eq!(store.block_iterator(AxisOrder::XYZ).count(), 64 - 8 - 16);
+
+            // removing an already emptied region is a no-op
+            store.remove_region(&inner).unwrap();
+            assert_eq!(store.block_iterator(AxisOrder::XYZ).count(), 64 - 8 - 16);
+            assert_eq!(store.boundary(), &boundary);
+        }
+    }
+
+    #[test]
+    fn test_iter_pages_skips_empty_pages() {
+        let boundary = Boundary::new(-32, 0, -32, 64, 16, 64);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let mut store = PagedBlockStore::new(boundary, 16, 16, 16, true, AxisOrder::XYZ);
+        assert_eq!(store.iter_pages().count(), 0);
+
+        // two blocks share the page at the origin, the others each land in a corner page
+        let positions = [
+            BlockPosition::new(-32, 0, -32),
+            BlockPosition::new(-31, 5, -30),
+            BlockPosition::new(31, 15, 31),
+            BlockPosition::new(0, 0, 0),
+        ];
+        for pos in &positions {
+            store.set_block_at(pos, stone.clone()).unwrap();
+        }
+        let mut keys: Vec<PageKey> = store.iter_pages().map(|(key, _)| key).collect();
+        keys.sort_by_key(|key| (key.x, key.y, key.z));
+        assert_eq!(keys, vec![
+            PageKey { x: 0, y: 0, z: 0 },
+            PageKey { x: 2, y: 0, z: 2 },
+            PageKey { x: 3, y: 0, z: 3 },
+        ]);
+
+        store.remove_block_at(positions[2]).unwrap();
+        assert_eq!(store.iter_pages().count(), 2);
+    }
+
+    #[test]
+    fn test_page_key_decodes_negative_indices() {
+        let mut store = PagedBlockStore::new_empty_resizable();
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        store.set_block_at(&BlockPosition::new(-20, 3, 40), stone.clone()).unwrap();
+        let keys: Vec<PageKey> = store.iter_pages().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![PageKey { x: -2, y: 0, z: 2 }]);
+
+        store.freeze_boundary();
+        assert_eq!(store.boundary(), &Boundary::new(-20, 3, 40, 1, 1, 1));
+    }
+
+    #[test]
+    fn test_capacity_hint_avoids_rehashing() {
+        let boundary = Boundary::new(-10, 0, 5, 200, 40, 150);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let mut store = PagedBlockStore::builder()
+            .boundary(boundary)
+            .fixed(true)
+            .capacity_hint(boundary.volume())
+            .build();
+        let capacity = store.pages.capacity();
+        for pos in boundary.iter(AxisOrder::XYZ) {
+            store.set_block_at(&pos, stone.clone()).unwrap();
+        }
+        assert_eq!(store.pages.capacity(), capacity);
+        assert_eq!(store.block_iterator(AxisOrder::XYZ).count(), boundary.volume());
+
+        let unhinted = PagedBlockStore::builder().boundary(boundary).fixed(true).build();
+        assert!(unhinted.pages.capacity() >= DEFAULT_PAGE_CAPACITY);
+        let huge = PagedBlockStore::builder().boundary(Boundary::new_from_size(1 << 20, 1 << 10, 1 << 10))
+            .capacity_hint(usize::MAX)
+            .build();
+        assert!(huge.pages.capacity() < 2 * MAX_PREALLOCATED_PAGES);
+    }
+
+    #[test]
+    fn test_clear() {
+        let boundary = Boundary::new(-4, 0, -4, 8, 4, 8);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        let mut paged = PagedBlockStore::new_for_fixed_boundary(boundary);
+        let mut sparse = SparseBlockStore::new(boundary, true);
+        for store in [&mut paged as &mut dyn BlockStore, &mut sparse] {
+            for pos in boundary.iter(AxisOrder::XYZ) {
+                store.set_block_at(&pos, stone.clone()).unwrap();
+            }
+            store.clear();
+            assert_eq!(store.block_iterator(AxisOrder::XYZ).count(), 0);
+            assert!(boundary.iter(AxisOrder::XYZ).all(|pos| store.block_at(&pos).unwrap().is_none()));
+            assert!(!store.contains_state(&stone));
+            assert_eq!(store.boundary(), &boundary);
+            assert!(!store.resizable());
+
+            // the store is reusable after clearing
+            store.set_block_at(&BlockPosition::new(0, 0, 0), dirt.clone()).unwrap();
+            assert_eq!(store.distinct_states(), vec![dirt.clone()]);
+        }
+    }
+
     #[test]
     fn test_lazy_palette_block_store() {
         let boundary = Boundary::new(0, 0, 0, 10, 10, 10);
@@ -526,6 +1182,25 @@ mod tests {
         assert!(retrieved.is_none());
     }
 
+    #[test]
+    fn test_lazy_palette_block_store_with_real_states() {
+        let boundary = Boundary::new(0, 0, 0, 4, 4, 4);
+        let mut inner_store = Box::new(SparseBlockStore::new(boundary, true));
+        let air_pos = BlockPosition::new(0, 0, 0);
+        let log_pos = BlockPosition::new(1, 0, 0);
+        let log = Rc::new(BlockState::from_str("minecraft:oak_log[axis=y]").unwrap());
+        inner_store.set_block_at(&air_pos, BlockState::air_rc()).unwrap();
+        inner_store.set_block_at(&log_pos, log.clone()).unwrap();
+        let mut lazy_store = LazyPaletteBlockStoreWrapper::from(inner_store);
+        let unknown_pos = BlockPosition::new(2, 0, 0);
+        lazy_store.set_unknown_block(&unknown_pos, 3).unwrap();
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        lazy_store.set_actual_palette(HashMap::from([(3, stone.clone())]));
+        assert_eq!(lazy_store.block_at(&air_pos).unwrap(), Some(BlockState::air_rc()));
+        assert_eq!(lazy_store.block_at(&log_pos).unwrap(), Some(log));
+        assert_eq!(lazy_store.block_at(&unknown_pos).unwrap(), Some(stone));
+    }
+
     #[test]
     fn test_large_page_store() {
         let boundary = Boundary::new(0, 0, 0, 11, 41, 125);
@@ -6,6 +6,17 @@ pub trait Page {
     fn store(&mut self, x: i32, y: i32, z: i32, state: u16) -> Result<(), String>;
 
     fn erase(&mut self, x: i32, y: i32, z: i32) -> Result<(), String>;
+
+    /// Calls `f` with the palette index of every occupied cell.
+    /// Has no default since the trait does not expose the page size to visit cells by coordinate.
+    fn for_each_state(&self, f: &mut dyn FnMut(u16));
+
+    /// Replaces the palette index `i` of every occupied cell with `mapping[i]`.
+    /// Has no default for the same reason as `for_each_state`.
+    fn remap_states(&mut self, mapping: &[u16]);
+
+    /// Whether no cell of the page is occupied, e.g. after all its blocks were erased.
+    fn is_empty(&self) -> bool;
 }
 
 pub struct ArrayPage {
@@ -72,4 +83,24 @@ impl Page for ArrayPage {
             Err("No block to erase at given coordinates".to_string())
         }
     }
+
+    fn for_each_state(&self, f: &mut dyn FnMut(u16)) {
+        for &value in &self.data {
+            if value != 0 {
+                f(value - 1);
+            }
+        }
+    }
+
+    fn remap_states(&mut self, mapping: &[u16]) {
+        for value in self.data.iter_mut() {
+            if *value != 0 {
+                *value = mapping[(*value - 1) as usize] + 1;
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nnz == 0
+    }
 }
\ No newline at end of file
@@ -2,6 +2,8 @@ use crate::common::{Block, Boundary};
 use crate::stream::mojang_reader::MojangSchematicInputStream;
 use crate::stream::shared_stream::{SharedStream, VirtualReader};
 use crate::stream::sponge_reader::SpongeSchematicInputStream;
+use crate::stream::error::SchematicError;
+use crate::stream::format::SchematicFormat;
 use crate::stream::stream::SchematicInputStream;
 use crate::stream::vxl_reader::VXLSchematicInputStream;
 use std::cell::RefCell;
@@ -21,7 +23,7 @@ impl SchematicInputStream for AnySchematicInputStream {
         buffer: &mut Vec<Block>,
         offset: usize,
         length: usize,
-    ) -> Result<Option<usize>, String> {
+    ) -> Result<Option<usize>, SchematicError> {
         if self.options.len() == 1 {
             return self.read_from_sole_provider(buffer, offset, length);
         }
@@ -51,7 +53,7 @@ impl SchematicInputStream for AnySchematicInputStream {
                 if self.closed {
                     Ok(None)
                 } else {
-                    Err("No matching format found".to_string())
+                    Err("No matching format found".into())
                 }
             },
             1 => self.read_from_sole_provider(buffer, offset, length),
@@ -59,17 +61,55 @@ impl SchematicInputStream for AnySchematicInputStream {
         }
     }
 
-    fn boundary(&mut self) -> Result<Option<Boundary>, String> {
+    /// Asks every remaining candidate for its boundary and drops those that fail to parse a header.
+    /// Each candidate reads from its own fork of the source, so this does not disturb later reads,
+    /// but a format whose header cannot be read is discarded for good, even when called early.
+    /// Returns the boundary once a single candidate remains, or when all remaining candidates agree.
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
         if self.options.len() == 1 {
             return self.options[0].0.boundary();
         }
-        self.options.retain_mut(|(opt, _)| opt.boundary().is_ok());
-        if self.options.len() == 1 {
-            self.options[0].0.boundary()
-        } else if self.options.is_empty() {
-            Err("No matching format found".to_string())
-        } else {
-            Ok(None)
+        let mut boundaries = Vec::new();
+        self.options.retain_mut(|(opt, _)| match opt.boundary() {
+            Ok(boundary) => {
+                boundaries.push(boundary);
+                true
+            }
+            Err(_) => false,
+        });
+        match boundaries.split_first() {
+            None => Err("No matching format found".into()),
+            Some((first, rest)) if rest.iter().all(|b| b == first) => Ok(*first),
+            Some(_) => Ok(None),
+        }
+    }
+
+    /// Only known once the candidates are narrowed down to a single format.
+    fn estimated_total(&mut self) -> Result<Option<usize>, SchematicError> {
+        match self.options.as_mut_slice() {
+            [(sole, _)] => sole.estimated_total(),
+            _ => Ok(None),
+        }
+    }
+
+    /// Only possible once the candidates are narrowed down to a single format.
+    /// Blocks already peeked from that format are dropped, since the seek is absolute.
+    fn seek_to(&mut self, block_index: usize) -> Result<(), SchematicError> {
+        match self.options.as_mut_slice() {
+            [(sole, peek_buf)] => {
+                sole.seek_to(block_index)?;
+                peek_buf.clear();
+                Ok(())
+            }
+            _ => Err("Cannot seek before the schematic format is known".into()),
+        }
+    }
+
+    /// Only known once the candidates are narrowed down to a single format.
+    fn format(&self) -> Option<SchematicFormat> {
+        match self.options.as_slice() {
+            [(sole, _)] => sole.format(),
+            _ => None,
         }
     }
 }
@@ -113,7 +153,7 @@ impl AnySchematicInputStream {
         buffer: &mut Vec<Block>,
         _offset: usize,
         length: usize,
-    ) -> Result<Option<usize>, String> {
+    ) -> Result<Option<usize>, SchematicError> {
         let (stream, peek_buf) = &mut self.options[0];
         if !peek_buf.is_empty() {
             let count = min(peek_buf.len(), length);
@@ -126,7 +166,9 @@ impl AnySchematicInputStream {
 
 #[cfg(test)]
 mod tests {
+    use crate::common::Boundary;
     use crate::stream::any_reader::AnySchematicInputStream;
+    use crate::stream::sponge_reader::SpongeSchematicInputStream;
     use crate::stream::stream::SchematicInputStream;
     use flate2::read::GzDecoder;
 
@@ -139,4 +181,17 @@ mod tests {
         let blocks = any_stream.read_to_end_into_vec().expect("Failed to read schematic");
         assert!(!blocks.is_empty(), "Expected to read some blocks from the schematic");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_any_reader_boundary_before_read() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let mut any_stream = AnySchematicInputStream::new_from_known(GzDecoder::new(TREE_SCHEMATIC));
+        assert_eq!(any_stream.boundary().unwrap(), Some(Boundary::new_from_size(16, 16, 16)));
+
+        let blocks = any_stream.read_to_end_into_vec().unwrap();
+        let expected = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC))
+            .read_to_end_into_vec()
+            .unwrap();
+        assert_eq!(blocks, expected);
+    }
+}
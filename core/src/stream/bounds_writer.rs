@@ -0,0 +1,67 @@
+use crate::common::{Block, Boundary};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+
+/// Rejects writes containing a block outside the declared boundary before any of them reach
+/// the inner stream. Fixed size formats would otherwise drop or misplace such blocks, so this
+/// catches translation and offset mistakes where they happen.
+pub struct BoundsCheckingOutputStream {
+    inner: Box<dyn SchematicOutputStream>,
+    boundary: Boundary,
+}
+
+impl BoundsCheckingOutputStream {
+    pub fn new(inner: Box<dyn SchematicOutputStream>, boundary: Boundary) -> Self {
+        BoundsCheckingOutputStream { inner, boundary }
+    }
+}
+
+impl SchematicOutputStream for BoundsCheckingOutputStream {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        if let Some(block) = blocks.iter().find(|block| !self.boundary.contains(&block.position)) {
+            return Err(SchematicError::OutOfBounds(format!(
+                "Block {} at {} is outside the declared boundary {:?}",
+                block.state, block.position, self.boundary
+            )));
+        }
+        self.inner.write(blocks)
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) -> Result<(), SchematicError> {
+        self.inner.set_boundary(boundary)
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        self.inner.flush()
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        self.inner.complete()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState, Boundary};
+    use crate::stream::bounds_writer::BoundsCheckingOutputStream;
+    use crate::stream::error::SchematicError;
+    use crate::stream::stream::SchematicOutputStream;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_out_of_bounds_block_is_rejected() {
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let sink = Rc::new(RefCell::new(Vec::new()));
+        let mut writer = BoundsCheckingOutputStream::new(Box::new(sink.clone()), Boundary::new(0, 0, 0, 4, 4, 4));
+        let inside = Block::new(stone.clone(), BlockPosition::new(3, 3, 3));
+        assert_eq!(writer.write(std::slice::from_ref(&inside)).unwrap(), 1);
+
+        let outside = Block::new(stone.clone(), BlockPosition::new(4, 0, 0));
+        let error = writer.write(&[inside.clone(), outside]).unwrap_err();
+        assert!(matches!(error, SchematicError::OutOfBounds(_)));
+        assert!(error.to_string().contains("(4, 0, 0)"), "Unexpected error: {}", error);
+        // the rejected write forwards nothing, not even its valid blocks
+        assert_eq!(*sink.borrow(), vec![inside]);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::common::Block;
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+use std::collections::HashMap;
+
+/// Splits the written blocks into cubic chunks of `chunk_size`, each going to its own output stream.
+/// Outputs are created lazily by the factory, which receives the chunk coordinates.
+pub struct ChunkedOutputStream {
+    chunk_size: i32,
+    factory: Box<dyn Fn(i32, i32, i32) -> Box<dyn SchematicOutputStream>>,
+    outputs: HashMap<(i32, i32, i32), Box<dyn SchematicOutputStream>>,
+}
+
+impl ChunkedOutputStream {
+    pub fn new(
+        chunk_size: i32,
+        factory: Box<dyn Fn(i32, i32, i32) -> Box<dyn SchematicOutputStream>>,
+    ) -> Result<Self, String> {
+        if chunk_size <= 0 {
+            return Err(format!("Chunked: Chunk size must be positive, got {}", chunk_size));
+        }
+        Ok(ChunkedOutputStream {
+            chunk_size,
+            factory,
+            outputs: HashMap::new(),
+        })
+    }
+
+    fn chunk_of(&self, block: &Block) -> (i32, i32, i32) {
+        block.position.chunk_coords(self.chunk_size)
+    }
+}
+
+impl SchematicOutputStream for ChunkedOutputStream {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        let mut by_chunk: HashMap<(i32, i32, i32), Vec<Block>> = HashMap::new();
+        for block in blocks {
+            by_chunk.entry(self.chunk_of(block)).or_default().push(block.clone());
+        }
+        let mut written = 0;
+        for (chunk, chunk_blocks) in by_chunk {
+            let output = self.outputs
+                .entry(chunk)
+                .or_insert_with(|| (self.factory)(chunk.0, chunk.1, chunk.2));
+            written += output.write(&chunk_blocks)?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        for output in self.outputs.values_mut() {
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        for output in self.outputs.values_mut() {
+            output.complete()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState};
+    use crate::stream::chunked_writer::ChunkedOutputStream;
+    use crate::stream::stream::SchematicOutputStream;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    /// The blocks each chunk sink received, by chunk coordinate.
+    type ChunkSinks = Rc<RefCell<HashMap<(i32, i32, i32), Rc<RefCell<Vec<Block>>>>>>;
+
+    #[test]
+    fn test_blocks_land_in_their_chunk() {
+        let sinks: ChunkSinks = Rc::new(RefCell::new(HashMap::new()));
+        let factory_sinks = sinks.clone();
+        let mut chunked = ChunkedOutputStream::new(16, Box::new(move |x, y, z| {
+            let sink = Rc::new(RefCell::new(Vec::new()));
+            factory_sinks.borrow_mut().insert((x, y, z), sink.clone());
+            Box::new(sink) as Box<dyn SchematicOutputStream>
+        })).unwrap();
+
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks = vec![
+            Block::new(stone.clone(), BlockPosition::new(0, 0, 0)),
+            Block::new(stone.clone(), BlockPosition::new(15, 15, 15)),
+            Block::new(stone.clone(), BlockPosition::new(16, 0, 0)),
+            Block::new(stone.clone(), BlockPosition::new(-1, 0, 0)),
+            Block::new(stone.clone(), BlockPosition::new(5, 40, -17)),
+        ];
+        assert_eq!(chunked.write(&blocks).unwrap(), blocks.len());
+        chunked.complete().unwrap();
+
+        let sinks = sinks.borrow();
+        assert_eq!(sinks.len(), 4);
+        let positions = |chunk: (i32, i32, i32)| -> Vec<BlockPosition> {
+            sinks[&chunk].borrow().iter().map(|b| b.position).collect()
+        };
+        assert_eq!(positions((0, 0, 0)), vec![BlockPosition::new(0, 0, 0), BlockPosition::new(15, 15, 15)]);
+        assert_eq!(positions((1, 0, 0)), vec![BlockPosition::new(16, 0, 0)]);
+        assert_eq!(positions((-1, 0, 0)), vec![BlockPosition::new(-1, 0, 0)]);
+        assert_eq!(positions((0, 2, -2)), vec![BlockPosition::new(5, 40, -17)]);
+    }
+}
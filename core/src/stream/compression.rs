@@ -0,0 +1,124 @@
+use crate::stream::error::SchematicError;
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{BufRead, Read, Write};
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Container compression wrapped around a schematic file.
+/// `Zstd` is only usable when the crate is built with the `zstd` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionAlgo {
+    Gzip,
+    Zstd,
+    None,
+}
+
+impl CompressionAlgo {
+    /// Parses `"gzip"`, `"zstd"` or `"none"`, ignoring case.
+    pub fn from_name(name: &str) -> Result<Self, SchematicError> {
+        match name.to_ascii_lowercase().as_str() {
+            "gzip" => Ok(CompressionAlgo::Gzip),
+            "zstd" => Ok(CompressionAlgo::Zstd),
+            "none" => Ok(CompressionAlgo::None),
+            _ => Err(format!("Unknown compression algorithm: {}", name).into()),
+        }
+    }
+
+    /// Guesses the compression from the first bytes of a file, falling back to `None`.
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&ZSTD_MAGIC) {
+            CompressionAlgo::Zstd
+        } else if header.starts_with(&GZIP_MAGIC) {
+            CompressionAlgo::Gzip
+        } else {
+            CompressionAlgo::None
+        }
+    }
+
+    /// Wraps `reader` in the matching decoder.
+    /// Gzip input may consist of several concatenated members, which are read back to back.
+    pub fn decoder<'a, R: BufRead + 'a>(self, reader: R) -> Result<Box<dyn Read + 'a>, SchematicError> {
+        match self {
+            CompressionAlgo::Gzip => Ok(Box::new(MultiGzDecoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            CompressionAlgo::Zstd => Ok(Box::new(zstd::Decoder::with_buffer(reader)?)),
+            #[cfg(not(feature = "zstd"))]
+            CompressionAlgo::Zstd => Err(ZSTD_DISABLED.into()),
+            CompressionAlgo::None => Ok(Box::new(reader)),
+        }
+    }
+
+    /// Wraps `writer` in the matching encoder. The encoder finishes its trailer when dropped.
+    pub fn encoder<'a, W: Write + 'a>(self, writer: W) -> Result<Box<dyn Write + 'a>, SchematicError> {
+        match self {
+            CompressionAlgo::Gzip => Ok(Box::new(GzEncoder::new(writer, flate2::Compression::default()))),
+            #[cfg(feature = "zstd")]
+            CompressionAlgo::Zstd => Ok(Box::new(zstd::Encoder::new(writer, 0)?.auto_finish())),
+            #[cfg(not(feature = "zstd"))]
+            CompressionAlgo::Zstd => Err(ZSTD_DISABLED.into()),
+            CompressionAlgo::None => Ok(Box::new(writer)),
+        }
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+const ZSTD_DISABLED: &str = "zstd support is not enabled in this build";
+
+/// Peeks at the start of `reader` and wraps it in the decoder its magic bytes call for.
+pub fn detect_decoder<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn Read + 'a>, SchematicError> {
+    let algo = CompressionAlgo::detect(reader.fill_buf()?);
+    algo.decoder(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::compression::{detect_decoder, CompressionAlgo};
+    use crate::stream::sponge_reader::SpongeSchematicInputStream;
+    use crate::stream::stream::SchematicInputStream;
+    use flate2::read::GzDecoder;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_concatenated_gzip_members() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let mut raw = Vec::new();
+        GzDecoder::new(TREE_SCHEMATIC).read_to_end(&mut raw).unwrap();
+
+        let (first, second) = raw.split_at(raw.len() / 2);
+        let mut data = Vec::new();
+        for member in [first, second] {
+            let mut encoder = CompressionAlgo::Gzip.encoder(&mut data).unwrap();
+            encoder.write_all(member).unwrap();
+        }
+
+        let mut reader = SpongeSchematicInputStream::new(detect_decoder(data.as_slice()).unwrap());
+        let expected = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC))
+            .read_to_end_into_vec()
+            .unwrap();
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_round_trip_detected() {
+        let payload = b"voxels voxels voxels voxels".repeat(16);
+        let mut algos = vec![CompressionAlgo::Gzip, CompressionAlgo::None];
+        if cfg!(feature = "zstd") {
+            algos.push(CompressionAlgo::Zstd);
+        }
+        for algo in algos {
+            let mut data = Vec::new();
+            {
+                let mut encoder = algo.encoder(&mut data).unwrap();
+                encoder.write_all(&payload).unwrap();
+            }
+            assert_eq!(CompressionAlgo::detect(&data), algo);
+            let mut decoded = Vec::new();
+            detect_decoder(data.as_slice()).unwrap().read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, payload);
+        }
+        assert!(CompressionAlgo::from_name("lz4").is_err());
+        assert_eq!(CompressionAlgo::from_name("ZSTD").unwrap(), CompressionAlgo::Zstd);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::common::Block;
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+use std::io::Write;
+
+/// Writes blocks as `x,y,z,blockstate` rows, one block per line.
+pub struct CsvSchematicOutputStream<W: Write> {
+    writer: W,
+    header_written: bool,
+}
+
+impl<W: Write> CsvSchematicOutputStream<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            header_written: false,
+        }
+    }
+
+    fn write_header(&mut self) -> Result<(), String> {
+        if !self.header_written {
+            writeln!(self.writer, "x,y,z,blockstate").map_err(|e| format!("CSV: Failed to write header: {}", e))?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+/// Quotes a field if it contains a delimiter, quote or line break, doubling embedded quotes.
+fn quote_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl<W: Write> SchematicOutputStream for CsvSchematicOutputStream<W> {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        self.write_header()?;
+        for block in blocks {
+            writeln!(
+                self.writer,
+                "{},{},{},{}",
+                block.position.x(),
+                block.position.y(),
+                block.position.z(),
+                quote_field(&block.state.to_string())
+            ).map_err(|e| format!("CSV: Failed to write block: {}", e))?;
+        }
+        Ok(blocks.len())
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        self.writer.flush().map_err(SchematicError::Io)
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        self.write_header()?;
+        self.writer.flush().map_err(SchematicError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState};
+    use crate::stream::csv_writer::CsvSchematicOutputStream;
+    use crate::stream::stream::SchematicOutputStream;
+    use std::rc::Rc;
+
+    fn parse_line(line: &str) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    current.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(std::mem::take(&mut current)),
+                _ => current.push(c),
+            }
+        }
+        fields.push(current);
+        fields
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let blocks = vec![
+            Block::new(Rc::new(BlockState::from_str("minecraft:stone").unwrap()), BlockPosition::new(0, 1, 2)),
+            Block::new(
+                Rc::new(BlockState::from_str("minecraft:oak_stairs[facing=north,half=top]").unwrap()),
+                BlockPosition::new(-3, 64, 7),
+            ),
+        ];
+        let mut output = Vec::new();
+        let mut writer = CsvSchematicOutputStream::new(&mut output);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("x,y,z,blockstate"));
+        let parsed: Vec<Block> = lines
+            .map(|line| {
+                let fields = parse_line(line);
+                assert_eq!(fields.len(), 4);
+                let position = BlockPosition::new(
+                    fields[0].parse().unwrap(),
+                    fields[1].parse().unwrap(),
+                    fields[2].parse().unwrap(),
+                );
+                Block::new(Rc::new(BlockState::from_str(&fields[3]).unwrap()), position)
+            })
+            .collect();
+        assert_eq!(parsed, blocks);
+    }
+}
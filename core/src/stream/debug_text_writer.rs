@@ -0,0 +1,137 @@
+use crate::common::{Block, BlockPosition, BlockState, Boundary};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+
+/// Characters handed out to states in the order they are first drawn.
+const SYMBOLS: &str = "#@%&*+=ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const EMPTY_SYMBOL: char = '.';
+const OVERFLOW_SYMBOL: char = '?';
+
+/// Renders blocks as ASCII slices for eyeballing small schematics in tests and bug reports.
+/// Every Y layer is a grid with one row per z and one character per x, air and empty cells
+/// are `.`, and a legend mapping characters to states follows the last layer.
+/// Blocks are buffered until `complete`, so this is not meant for large schematics.
+pub struct DebugTextOutputStream<W: Write> {
+    writer: W,
+    boundary: Option<Boundary>,
+    blocks: HashMap<BlockPosition, Rc<BlockState>>,
+}
+
+impl<W: Write> DebugTextOutputStream<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            boundary: None,
+            blocks: HashMap::new(),
+        }
+    }
+
+    fn render(&self) -> String {
+        let boundary = match self.boundary {
+            Some(boundary) => boundary,
+            None => {
+                let blocks: Vec<Block> = self.blocks.iter()
+                    .map(|(pos, state)| Block::new(state.clone(), *pos))
+                    .collect();
+                Boundary::from_blocks(&blocks)
+            }
+        };
+        let mut symbols = SYMBOLS.chars();
+        let mut legend: Vec<(char, Rc<BlockState>)> = Vec::new();
+        let mut assigned: HashMap<Rc<BlockState>, char> = HashMap::new();
+        let mut layers = Vec::new();
+        for y in boundary.min_y..boundary.min_y + boundary.d_y {
+            let mut layer = format!("y={}\n", y);
+            for z in boundary.min_z..boundary.min_z + boundary.d_z {
+                for x in boundary.min_x..boundary.min_x + boundary.d_x {
+                    let symbol = match self.blocks.get(&BlockPosition::new(x, y, z)) {
+                        Some(state) if !state.is_air() => *assigned.entry(state.clone()).or_insert_with(|| {
+                            let symbol = symbols.next().unwrap_or(OVERFLOW_SYMBOL);
+                            legend.push((symbol, state.clone()));
+                            symbol
+                        }),
+                        _ => EMPTY_SYMBOL,
+                    };
+                    layer.push(symbol);
+                }
+                layer.push('\n');
+            }
+            layers.push(layer);
+        }
+        let mut text = layers.join("\n");
+        text.push('\n');
+        for (symbol, state) in legend {
+            text.push_str(&format!("{} = {}\n", symbol, state));
+        }
+        text
+    }
+}
+
+impl<W: Write> SchematicOutputStream for DebugTextOutputStream<W> {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        for block in blocks {
+            self.blocks.insert(block.position, block.state.clone());
+        }
+        Ok(blocks.len())
+    }
+
+    /// Draws exactly this boundary instead of the smallest box around the written blocks.
+    fn set_boundary(&mut self, boundary: Boundary) -> Result<(), SchematicError> {
+        self.boundary = Some(boundary);
+        Ok(())
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        let text = self.render();
+        self.writer.write_all(text.as_bytes()).map_err(SchematicError::Io)?;
+        self.writer.flush().map_err(SchematicError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState, Boundary};
+    use crate::stream::debug_text_writer::DebugTextOutputStream;
+    use crate::stream::stream::SchematicOutputStream;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_render_single_layer() {
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        let blocks = vec![
+            Block::new(stone.clone(), BlockPosition::new(0, 0, 0)),
+            Block::new(dirt.clone(), BlockPosition::new(1, 0, 0)),
+            Block::new(BlockState::air_rc(), BlockPosition::new(2, 0, 0)),
+            Block::new(stone.clone(), BlockPosition::new(1, 0, 1)),
+            Block::new(dirt.clone(), BlockPosition::new(2, 0, 2)),
+        ];
+        let mut output = Vec::new();
+        let mut writer = DebugTextOutputStream::new(&mut output);
+        writer.set_boundary(Boundary::new(0, 0, 0, 3, 1, 3)).unwrap();
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(text, "y=0\n#@.\n.#.\n..@\n\n# = minecraft:stone\n@ = minecraft:dirt\n");
+    }
+
+    #[test]
+    fn test_layers_are_separated() {
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks = vec![
+            Block::new(stone.clone(), BlockPosition::new(5, 3, 7)),
+            Block::new(stone.clone(), BlockPosition::new(6, 4, 7)),
+        ];
+        let mut output = Vec::new();
+        let mut writer = DebugTextOutputStream::new(&mut output);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(text, "y=3\n#.\n\ny=4\n.#\n\n# = minecraft:stone\n");
+    }
+}
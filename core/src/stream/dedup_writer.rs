@@ -0,0 +1,72 @@
+use crate::common::{Block, Boundary};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+
+/// Drops a block if it repeats the position and state of the block emitted right before it,
+/// guarding the inner stream against accidental double writes.
+pub struct DedupOutputStream {
+    inner: Box<dyn SchematicOutputStream>,
+    last: Option<Block>,
+}
+
+impl DedupOutputStream {
+    pub fn new(inner: Box<dyn SchematicOutputStream>) -> Self {
+        DedupOutputStream { inner, last: None }
+    }
+}
+
+impl SchematicOutputStream for DedupOutputStream {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        let mut accepted = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            if self.last.as_ref() == Some(block) {
+                continue;
+            }
+            self.last = Some(block.clone());
+            accepted.push(block.clone());
+        }
+        if accepted.is_empty() {
+            return Ok(0);
+        }
+        self.inner.write(&accepted)
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) -> Result<(), SchematicError> {
+        self.inner.set_boundary(boundary)
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        self.inner.flush()
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        self.inner.complete()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState};
+    use crate::stream::dedup_writer::DedupOutputStream;
+    use crate::stream::stream::SchematicOutputStream;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_consecutive_duplicates_are_written_once() {
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        let first = Block::new(stone.clone(), BlockPosition::new(0, 0, 0));
+        let second = Block::new(dirt.clone(), BlockPosition::new(1, 0, 0));
+        let replaced = Block::new(stone.clone(), BlockPosition::new(1, 0, 0));
+
+        let sink = Rc::new(RefCell::new(Vec::new()));
+        let mut dedup = DedupOutputStream::new(Box::new(sink.clone()));
+        assert_eq!(dedup.write(&[first.clone(), first.clone(), second.clone()]).unwrap(), 2);
+        // the duplicate may also straddle two writes
+        assert_eq!(dedup.write(&[second.clone(), replaced.clone()]).unwrap(), 1);
+        dedup.complete().unwrap();
+
+        assert_eq!(*sink.borrow(), vec![first, second, replaced]);
+    }
+}
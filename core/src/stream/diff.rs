@@ -0,0 +1,109 @@
+use crate::common::{AxisOrder, BlockPosition, Boundary, Region};
+use crate::store::blockstore::{BlockStore, PagedBlockStore};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicInputStream;
+
+/// A position where two schematics disagree, with the state string on each side (`None` for empty).
+pub type BlockDifference = (BlockPosition, Option<String>, Option<String>);
+
+/// Loads both streams and compares them cell by cell over the union of their boundaries.
+/// Air and missing blocks are both reported as `None`.
+pub fn diff_streams(
+    a: &mut dyn SchematicInputStream,
+    b: &mut dyn SchematicInputStream,
+) -> Result<Vec<BlockDifference>, SchematicError> {
+    let store_a = load_into_store(a)?;
+    let store_b = load_into_store(b)?;
+
+    let mut differences = Vec::new();
+    for pos in union(store_a.boundary(), store_b.boundary()).iter(AxisOrder::XYZ) {
+        let state_a = state_string_at(&store_a, &pos)?;
+        let state_b = state_string_at(&store_b, &pos)?;
+        if state_a != state_b {
+            differences.push((pos, state_a, state_b));
+        }
+    }
+    Ok(differences)
+}
+
+fn load_into_store(stream: &mut dyn SchematicInputStream) -> Result<PagedBlockStore, SchematicError> {
+    let mut store = match stream.boundary()? {
+        Some(boundary) => PagedBlockStore::new_for_fixed_boundary(boundary),
+        None => PagedBlockStore::new_empty_resizable(),
+    };
+    stream.read_to_end(&mut store)?;
+    Ok(store)
+}
+
+fn state_string_at(store: &PagedBlockStore, pos: &BlockPosition) -> Result<Option<String>, SchematicError> {
+    if !store.contains(pos) {
+        return Ok(None);
+    }
+    Ok(store.block_at(pos)?
+        .filter(|state| !state.is_air())
+        .map(|state| state.to_string()))
+}
+
+fn union(a: &Boundary, b: &Boundary) -> Boundary {
+    if a.volume() == 0 {
+        return *b;
+    }
+    if b.volume() == 0 {
+        return *a;
+    }
+    Boundary::new_from_min_max(
+        a.min_x.min(b.min_x),
+        a.min_y.min(b.min_y),
+        a.min_z.min(b.min_z),
+        a.max_x().max(b.max_x()),
+        a.max_y().max(b.max_y()),
+        a.max_z().max(b.max_z()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
+    use crate::stream::diff::diff_streams;
+    use crate::stream::stream::SchematicOutputStream;
+    use crate::stream::vxl_reader::VXLSchematicInputStream;
+    use crate::stream::vxl_writer::VXLSchematicOutputStream;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    fn to_vxl(blocks: &[Block], boundary: Boundary) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, AxisOrder::XYZ, boundary);
+        writer.write(blocks).unwrap();
+        writer.complete().unwrap();
+        data
+    }
+
+    #[test]
+    fn test_diff_single_modified_block() {
+        let boundary = Boundary::new_from_size(4, 4, 4);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let glass = Rc::new(BlockState::from_str("minecraft:glass").unwrap());
+        let original: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .filter(|pos| pos.y() == 0)
+            .map(|pos| Block::new(stone.clone(), pos))
+            .collect();
+        let mut modified = original.clone();
+        modified[5].state = glass.clone();
+        let changed_pos = modified[5].position;
+
+        let mut a = VXLSchematicInputStream::new(Cursor::new(to_vxl(&original, boundary)));
+        let mut b = VXLSchematicInputStream::new(Cursor::new(to_vxl(&modified, boundary)));
+        let differences = diff_streams(&mut a, &mut b).unwrap();
+        assert_eq!(differences, vec![(
+            changed_pos,
+            Some("minecraft:stone".to_string()),
+            Some("minecraft:glass".to_string()),
+        )]);
+
+        let mut a = VXLSchematicInputStream::new(Cursor::new(to_vxl(&original, boundary)));
+        let mut b = VXLSchematicInputStream::new(Cursor::new(to_vxl(&original[1..], boundary)));
+        let differences = diff_streams(&mut a, &mut b).unwrap();
+        assert_eq!(differences, vec![(BlockPosition::new(0, 0, 0), Some("minecraft:stone".to_string()), None)]);
+    }
+}
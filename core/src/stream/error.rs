@@ -0,0 +1,61 @@
+use std::fmt::{Display, Formatter};
+
+/// Errors produced while reading or writing schematic streams.
+#[derive(Debug)]
+pub enum SchematicError {
+    /// The underlying reader or writer failed.
+    Io(std::io::Error),
+    /// The NBT structure could not be decoded or is missing required tags.
+    MalformedNbt(String),
+    /// The file declares a format version this crate cannot handle.
+    UnsupportedVersion(i32),
+    /// A position or index lies outside the schematic boundary.
+    OutOfBounds(String),
+    /// Any other malformed or unexpected content.
+    Parse(String),
+}
+
+impl Display for SchematicError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchematicError::Io(e) => write!(f, "I/O error: {}", e),
+            SchematicError::MalformedNbt(msg) => write!(f, "Malformed NBT: {}", msg),
+            SchematicError::UnsupportedVersion(version) => write!(f, "Unsupported version: {}", version),
+            SchematicError::OutOfBounds(msg) => write!(f, "Out of bounds: {}", msg),
+            SchematicError::Parse(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SchematicError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchematicError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SchematicError {
+    fn from(e: std::io::Error) -> Self {
+        SchematicError::Io(e)
+    }
+}
+
+impl From<String> for SchematicError {
+    fn from(msg: String) -> Self {
+        SchematicError::Parse(msg)
+    }
+}
+
+impl From<&str> for SchematicError {
+    fn from(msg: &str) -> Self {
+        SchematicError::Parse(msg.to_string())
+    }
+}
+
+impl From<SchematicError> for String {
+    fn from(e: SchematicError) -> Self {
+        e.to_string()
+    }
+}
@@ -0,0 +1,142 @@
+use crate::common::{AxisOrder, Boundary};
+use crate::stream::any_reader::AnySchematicInputStream;
+use crate::stream::compression::{detect_decoder, CompressionAlgo};
+use crate::stream::error::SchematicError;
+use crate::stream::format::SchematicFormat;
+use crate::stream::litematic_reader::LitematicaSchematicInputStream;
+use crate::stream::mcedit_reader::MCEditSchematicInputStream;
+use crate::stream::mojang_reader::MojangSchematicInputStream;
+use crate::stream::mojang_writer::MojangSchematicOutputStream;
+use crate::stream::sponge_reader::SpongeSchematicInputStream;
+use crate::stream::sponge_writer::SpongeSchematicOutputStream;
+use crate::stream::stream::{SchematicInputStream, SchematicOutputStream};
+use crate::stream::vxl_reader::VXLSchematicInputStream;
+use crate::stream::vxl_writer::VXLSchematicOutputStream;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Opens the schematic at `path`, undoing any gzip or zstd compression detected from its first bytes.
+/// With `format` set, that reader is used directly. Otherwise every known format is tried
+/// through `AnySchematicInputStream`, which can only tell formats apart once it reads.
+pub fn open_file(path: &Path, format: Option<SchematicFormat>) -> Result<Box<dyn SchematicInputStream>, SchematicError> {
+    let file = File::open(path)?;
+    let reader = detect_decoder(BufReader::new(file))?;
+    Ok(match format {
+        Some(SchematicFormat::Mojang) => Box::new(MojangSchematicInputStream::new(reader)),
+        Some(SchematicFormat::Sponge) => Box::new(SpongeSchematicInputStream::new(reader)),
+        Some(SchematicFormat::Vxl) => Box::new(VXLSchematicInputStream::new(reader)),
+        Some(SchematicFormat::Litematica) => Box::new(LitematicaSchematicInputStream::new(reader)),
+        Some(SchematicFormat::McEdit) => Box::new(MCEditSchematicInputStream::new(reader)),
+        None => Box::new(AnySchematicInputStream::new_from_known(reader)),
+    })
+}
+
+/// Creates (or truncates) the file at `path` and returns a gzip compressed writer for `format`.
+/// The gzip trailer is written when the returned stream is dropped, after `complete`.
+pub fn save_file(
+    path: &Path,
+    format: SchematicFormat,
+    boundary: Option<Boundary>,
+) -> Result<Box<dyn SchematicOutputStream>, SchematicError> {
+    // fail before creating the file, not after
+    check_writable(format, boundary)?;
+    let file = File::create(path)?;
+    let encoder = CompressionAlgo::Gzip.encoder(BufWriter::new(file))?;
+    output_stream_for(BufWriter::new(encoder), format, boundary)
+}
+
+/// Builds the writer for `format` on top of `writer`. Sponge and VXL need the boundary up front,
+/// Mojang only uses it if given.
+pub fn output_stream_for<W: Write + 'static>(
+    writer: W,
+    format: SchematicFormat,
+    boundary: Option<Boundary>,
+) -> Result<Box<dyn SchematicOutputStream>, SchematicError> {
+    check_writable(format, boundary)?;
+    Ok(match (format, boundary) {
+        (SchematicFormat::Mojang, boundary) => {
+            let mut stream = MojangSchematicOutputStream::new(writer);
+            if let Some(boundary) = boundary {
+                stream.set_boundary(boundary)?;
+            }
+            Box::new(stream)
+        }
+        (SchematicFormat::Sponge, Some(boundary)) => Box::new(SpongeSchematicOutputStream::new(writer, boundary)),
+        (SchematicFormat::Vxl, Some(boundary)) => {
+            Box::new(VXLSchematicOutputStream::new(writer, AxisOrder::preferred(), boundary))
+        }
+        _ => unreachable!("rejected by check_writable"),
+    })
+}
+
+fn check_writable(format: SchematicFormat, boundary: Option<Boundary>) -> Result<(), SchematicError> {
+    if !format.capabilities().writable {
+        return Err(format!("Writing {:?} schematics is not supported", format).into());
+    }
+    if boundary.is_none() && matches!(format, SchematicFormat::Sponge | SchematicFormat::Vxl) {
+        return Err(format!("A boundary is required to write {:?} schematics", format).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{AxisOrder, Block, BlockState, Boundary, Region};
+    use crate::stream::error::SchematicError;
+    use crate::stream::file::{open_file, save_file};
+    use crate::stream::format::SchematicFormat;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    const TREE_SCHEMATIC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/stream/test_schematics/tree.sponge");
+
+    #[test]
+    fn test_open_bundled_sponge_file() {
+        let mut known = open_file(Path::new(TREE_SCHEMATIC), Some(SchematicFormat::Sponge)).unwrap();
+        assert_eq!(known.format(), Some(SchematicFormat::Sponge));
+        assert_eq!(known.boundary().unwrap(), Some(Boundary::new_from_size(16, 16, 16)));
+        let expected = known.read_to_end_into_vec().unwrap();
+        assert!(!expected.is_empty());
+
+        let mut detected = open_file(Path::new(TREE_SCHEMATIC), None).unwrap();
+        assert_eq!(detected.read_to_end_into_vec().unwrap(), expected);
+        assert_eq!(detected.format(), Some(SchematicFormat::Sponge));
+    }
+
+    #[test]
+    fn test_open_missing_file_is_an_io_error() {
+        let result = open_file(Path::new("does/not/exist.schem"), None);
+        assert!(matches!(result, Err(SchematicError::Io(_))));
+    }
+
+    #[test]
+    fn test_save_vxl_and_reopen() {
+        let boundary = Boundary::new(-3, 60, 2, 4, 3, 5);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .step_by(4)
+            .map(|pos| Block::new(stone.clone(), pos))
+            .collect();
+        let path = std::env::temp_dir().join(format!("voxels-save-file-{}.vxl", std::process::id()));
+
+        let mut writer = save_file(&path, SchematicFormat::Vxl, Some(boundary)).unwrap();
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+        drop(writer);
+
+        let mut reader = open_file(&path, None).unwrap();
+        assert_eq!(reader.boundary().unwrap(), Some(boundary));
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), blocks);
+        assert_eq!(reader.format(), Some(SchematicFormat::Vxl));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_save_file_validates_before_creating() {
+        let path = std::env::temp_dir().join(format!("voxels-save-file-{}.schem", std::process::id()));
+        assert!(save_file(&path, SchematicFormat::Sponge, None).is_err());
+        assert!(save_file(&path, SchematicFormat::McEdit, Some(Boundary::new_from_size(1, 1, 1))).is_err());
+        assert!(!path.exists());
+    }
+}
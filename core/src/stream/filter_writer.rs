@@ -0,0 +1,74 @@
+use crate::common::{Block, Boundary};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+
+/// Forwards only the blocks accepted by the predicate to the inner stream.
+pub struct FilterOutputStream {
+    inner: Box<dyn SchematicOutputStream>,
+    predicate: Box<dyn Fn(&Block) -> bool>,
+}
+
+impl FilterOutputStream {
+    pub fn new(inner: Box<dyn SchematicOutputStream>, predicate: Box<dyn Fn(&Block) -> bool>) -> Self {
+        FilterOutputStream { inner, predicate }
+    }
+}
+
+impl SchematicOutputStream for FilterOutputStream {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        let accepted: Vec<Block> = blocks.iter()
+            .filter(|block| (self.predicate)(block))
+            .cloned()
+            .collect();
+        if accepted.is_empty() {
+            return Ok(0);
+        }
+        self.inner.write(&accepted)
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) -> Result<(), SchematicError> {
+        self.inner.set_boundary(boundary)
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        self.inner.flush()
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        self.inner.complete()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState};
+    use crate::stream::filter_writer::FilterOutputStream;
+    use crate::stream::stream::SchematicOutputStream;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_filter_keeps_only_matching_blocks() {
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        let torch = Rc::new(BlockState::from_str("minecraft:wall_torch[facing=north]").unwrap());
+        let blocks: Vec<Block> = (0..12)
+            .map(|x| {
+                let state = [&stone, &dirt, &torch][x as usize % 3];
+                Block::new(state.clone(), BlockPosition::new(x, 0, 0))
+            })
+            .collect();
+
+        let sink = Rc::new(RefCell::new(Vec::new()));
+        let mut filter = FilterOutputStream::new(
+            Box::new(sink.clone()),
+            Box::new(|block: &Block| block.state.matches("minecraft:stone")),
+        );
+        filter.write(&blocks).unwrap();
+        filter.complete().unwrap();
+
+        let written = sink.borrow();
+        assert_eq!(written.len(), 4);
+        assert!(written.iter().all(|block| block.state == stone));
+    }
+}
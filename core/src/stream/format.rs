@@ -0,0 +1,80 @@
+/// The schematic file formats this crate knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchematicFormat {
+    Mojang,
+    Sponge,
+    Vxl,
+    Litematica,
+    McEdit,
+}
+
+/// What a format supports, so callers can decide behavior without knowing the concrete reader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatCapabilities {
+    /// The reader supports `seek_to` in both directions. VXL readers only seek forward.
+    pub random_access: bool,
+    /// The boundary is known from the header, before any block is read.
+    pub boundary_before_read: bool,
+    /// Block data is stored as runs of equal states.
+    pub run_length_encoded: bool,
+    /// The crate can write this format.
+    pub writable: bool,
+}
+
+impl SchematicFormat {
+    pub fn capabilities(self) -> FormatCapabilities {
+        match self {
+            SchematicFormat::Mojang => FormatCapabilities {
+                random_access: false,
+                boundary_before_read: true,
+                run_length_encoded: false,
+                writable: true,
+            },
+            SchematicFormat::Sponge => FormatCapabilities {
+                random_access: true,
+                boundary_before_read: true,
+                run_length_encoded: false,
+                writable: true,
+            },
+            SchematicFormat::Vxl => FormatCapabilities {
+                random_access: false,
+                boundary_before_read: true,
+                run_length_encoded: true,
+                writable: true,
+            },
+            SchematicFormat::Litematica => FormatCapabilities {
+                random_access: false,
+                boundary_before_read: true,
+                run_length_encoded: false,
+                writable: false,
+            },
+            SchematicFormat::McEdit => FormatCapabilities {
+                random_access: true,
+                boundary_before_read: true,
+                run_length_encoded: false,
+                writable: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::format::SchematicFormat;
+    use crate::stream::mojang_reader::MojangSchematicInputStream;
+    use crate::stream::stream::SchematicInputStream;
+    use crate::stream::vxl_reader::VXLSchematicInputStream;
+
+    #[test]
+    fn test_format_capabilities() {
+        let vxl = VXLSchematicInputStream::new(&[][..]);
+        assert_eq!(vxl.format(), Some(SchematicFormat::Vxl));
+        assert!(SchematicFormat::Vxl.capabilities().run_length_encoded);
+
+        let mojang = MojangSchematicInputStream::new(&[][..]);
+        assert_eq!(mojang.format(), Some(SchematicFormat::Mojang));
+        assert!(SchematicFormat::Mojang.capabilities().writable);
+        assert!(!SchematicFormat::Mojang.capabilities().run_length_encoded);
+        assert!(!SchematicFormat::McEdit.capabilities().writable);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::common::{Block, Boundary};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// Forwards every block to the inner stream while hashing the ordered `(position, state)` sequence.
+/// The hash is FNV-1a over fixed byte encodings, so it is stable across runs and platforms.
+pub struct HashingOutputStream {
+    inner: Box<dyn SchematicOutputStream>,
+    hash: u64,
+}
+
+impl HashingOutputStream {
+    pub fn new(inner: Box<dyn SchematicOutputStream>) -> Self {
+        HashingOutputStream {
+            inner,
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    /// Hash of all blocks written so far, final once `complete()` returned.
+    pub fn content_hash(&self) -> u64 {
+        self.hash
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+impl SchematicOutputStream for HashingOutputStream {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        for block in blocks {
+            for coordinate in block.position.to_array() {
+                self.update(&coordinate.to_le_bytes());
+            }
+            self.update(block.state.to_string().as_bytes());
+            // state strings never contain NUL, so it cleanly separates one block from the next
+            self.update(&[0]);
+        }
+        self.inner.write(blocks)
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) -> Result<(), SchematicError> {
+        self.inner.set_boundary(boundary)
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        self.inner.flush()
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        self.inner.complete()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState};
+    use crate::stream::hashing_writer::HashingOutputStream;
+    use crate::stream::stream::SchematicOutputStream;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn hash_of(blocks: &[Block]) -> u64 {
+        let sink = Rc::new(RefCell::new(Vec::new()));
+        let mut hashing = HashingOutputStream::new(Box::new(sink.clone()));
+        // split the writes to check the hash does not depend on batching
+        let (head, tail) = blocks.split_at(blocks.len() / 2);
+        hashing.write(head).unwrap();
+        hashing.write(tail).unwrap();
+        hashing.complete().unwrap();
+        assert_eq!(*sink.borrow(), blocks);
+        hashing.content_hash()
+    }
+
+    #[test]
+    fn test_identical_sequences_hash_equal() {
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let stairs = Rc::new(BlockState::from_str("minecraft:oak_stairs[facing=north,half=top]").unwrap());
+        let blocks = vec![
+            Block::new(stone.clone(), BlockPosition::new(0, 0, 0)),
+            Block::new(stairs.clone(), BlockPosition::new(1, 0, 0)),
+            Block::new(stone.clone(), BlockPosition::new(-4, 12, 3)),
+        ];
+        assert_eq!(hash_of(&blocks), hash_of(&blocks.clone()));
+
+        let mut moved = blocks.clone();
+        moved[2].position = BlockPosition::new(-4, 12, 4);
+        assert_ne!(hash_of(&blocks), hash_of(&moved));
+
+        let mut changed = blocks.clone();
+        changed[1].state = Rc::new(BlockState::from_str("minecraft:oak_stairs[facing=south,half=top]").unwrap());
+        assert_ne!(hash_of(&blocks), hash_of(&changed));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::common::Block;
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+use std::io::Write;
+
+/// Writes one JSON object per line, `{"pos":[x,y,z],"state":"..."}`, for every block.
+pub struct JsonLinesOutputStream<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesOutputStream<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> SchematicOutputStream for JsonLinesOutputStream<W> {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        for block in blocks {
+            let state = serde_json::to_string(&block.state.to_string())
+                .map_err(|e| format!("JSON Lines: Failed to encode block state: {}", e))?;
+            writeln!(
+                self.writer,
+                r#"{{"pos":[{},{},{}],"state":{}}}"#,
+                block.position.x(),
+                block.position.y(),
+                block.position.z(),
+                state
+            ).map_err(|e| format!("JSON Lines: Failed to write block: {}", e))?;
+        }
+        Ok(blocks.len())
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        self.writer.flush().map_err(SchematicError::Io)
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        self.writer.flush().map_err(SchematicError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState};
+    use crate::stream::json_lines_writer::JsonLinesOutputStream;
+    use crate::stream::stream::SchematicOutputStream;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_json_lines_are_valid_json() {
+        let blocks = vec![
+            Block::new(Rc::new(BlockState::from_str("minecraft:stone").unwrap()), BlockPosition::new(0, 1, 2)),
+            Block::new(
+                Rc::new(BlockState::from_str("minecraft:oak_stairs[facing=north,half=top]").unwrap()),
+                BlockPosition::new(-3, 64, 7),
+            ),
+        ];
+        let mut output = Vec::new();
+        let mut writer = JsonLinesOutputStream::new(&mut output);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), blocks.len());
+        for (line, block) in lines.iter().zip(&blocks) {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            let object = value.as_object().unwrap();
+            assert_eq!(object.len(), 2);
+            assert_eq!(object["pos"], serde_json::json!(block.position.to_array()));
+            assert_eq!(object["state"], serde_json::json!(block.state.to_string()));
+        }
+    }
+}
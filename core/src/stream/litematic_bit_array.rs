@@ -6,17 +6,22 @@ pub struct LitematicaBitArray {
 }
 
 impl LitematicaBitArray {
-    pub fn new(size: usize, nbits: usize) -> Self {
+    /// Palette indices never need more bits than this, and it keeps `mask` far from overflowing.
+    const MAX_BITS: usize = 32;
+
+    pub fn new(size: usize, nbits: usize) -> Result<Self, String> {
+        Self::check_nbits(nbits)?;
         let s = ((nbits * size) as f64 / 64.0).ceil() as usize;
-        Self {
+        Ok(Self {
             size,
             nbits,
             array: vec![0; s],
             mask: (1u64 << nbits) - 1,
-        }
+        })
     }
 
     pub fn from_nbt(arr: Vec<i64>, size: usize, nbits: usize) -> Result<Self, String> {
+        Self::check_nbits(nbits)?;
         let expected_len = ((size * nbits) as f64 / 64.0).ceil() as usize;
         if expected_len != arr.len() {
             return Err(format!("Length mismatch: expected {}, got {}", expected_len, arr.len()));
@@ -65,9 +70,10 @@ impl LitematicaBitArray {
         self.array[start_arr_index] &= !(self.mask << start_bit_offset);
         self.array[start_arr_index] |= value << start_bit_offset;
         if start_arr_index != end_arr_index {
+            // the low bits went into the previous word, the rest fill the bottom of this one
             let end_offset = 64 - start_bit_offset;
-            let j1 = self.nbits - end_offset;
-            self.array[end_arr_index] = (self.array[end_arr_index] >> j1 << j1) | (value >> end_offset);
+            let high_mask = self.mask >> end_offset;
+            self.array[end_arr_index] = (self.array[end_arr_index] & !high_mask) | (value >> end_offset);
         }
         Ok(())
     }
@@ -75,4 +81,59 @@ impl LitematicaBitArray {
     pub fn len(&self) -> usize {
         self.size
     }
-}
\ No newline at end of file
+
+    fn check_nbits(nbits: usize) -> Result<(), String> {
+        if !(1..=Self::MAX_BITS).contains(&nbits) {
+            return Err(format!("Bits per entry must be between 1 and {}, got {}", Self::MAX_BITS, nbits));
+        }
+        Ok(())
+    }
+
+    /// Number of entries holding a value other than zero.
+    #[cfg(test)]
+    pub fn count_nonzero(&self) -> usize {
+        (0..self.size).filter(|&index| self.get(index) != Some(0)).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::litematic_bit_array::LitematicaBitArray;
+    use rand::{RngExt, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_random_round_trip_through_nbt() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1876);
+        for nbits in 2..=16 {
+            let size = 257;
+            let max = (1u64 << nbits) - 1;
+            let mut bits = LitematicaBitArray::new(size, nbits).unwrap();
+            let mut expected = vec![0u64; size];
+            // overwrite some entries several times so neighbours of cross-word values get rewritten too
+            for _ in 0..size * 3 {
+                let index = rng.random_range(0..size);
+                let value = if rng.random_bool(0.2) { 0 } else { rng.random_range(0..=max) };
+                bits.set(index, value).unwrap();
+                expected[index] = value;
+            }
+            assert!(bits.set(0, max + 1).is_err());
+            assert!(bits.set(size, 0).is_err());
+
+            let restored = LitematicaBitArray::from_nbt(bits.to_nbt_vec(), size, nbits).unwrap();
+            let values: Vec<u64> = (0..size).map(|index| restored.get(index).unwrap()).collect();
+            assert_eq!(values, expected, "nbits = {}", nbits);
+            assert_eq!(restored.count_nonzero(), expected.iter().filter(|&&v| v != 0).count());
+            assert_eq!(restored.get(size), None);
+        }
+    }
+
+    #[test]
+    fn test_nbits_out_of_range_is_rejected() {
+        assert!(LitematicaBitArray::new(16, 32).is_ok());
+        assert!(LitematicaBitArray::new(16, 0).is_err());
+        assert!(LitematicaBitArray::new(16, 33).is_err());
+        assert!(LitematicaBitArray::new(16, 64).is_err());
+        assert!(LitematicaBitArray::from_nbt(vec![0; 16], 16, 64).is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use crate::common::{Block, BlockPosition, BlockState, Boundary};
 use crate::stream::litematic_bit_array::LitematicaBitArray;
+use crate::stream::error::SchematicError;
+use crate::stream::format::SchematicFormat;
 use crate::stream::stream::SchematicInputStream;
 use fastnbt::stream::{Parser, Value};
 use fastnbt::Tag;
@@ -111,7 +113,10 @@ impl<R: std::io::Read> LitematicaSchematicInputStream<R> {
                     let s = self.read_xyz_compound()?;
                     // Litematica sizes can be negative, implying direction.
                     // We take absolute size for storage, origin logic handles position.
-                    size = (s.0.abs() as usize, s.1.abs() as usize, s.2.abs() as usize);
+                    // i32::MIN has no absolute value and stays negative, which `try_new` rejects.
+                    let local = Boundary::try_new(0, 0, 0, s.0.wrapping_abs(), s.1.wrapping_abs(), s.2.wrapping_abs())
+                        .map_err(|e| format!("Invalid Litematica region: {}", e))?;
+                    size = (local.d_x as usize, local.d_y as usize, local.d_z as usize);
                 }
 
                 Value::Compound(_) => depth += 1,
@@ -242,7 +247,7 @@ impl<R: std::io::Read> LitematicaSchematicInputStream<R> {
 }
 
 impl<R: std::io::Read> SchematicInputStream for LitematicaSchematicInputStream<R> {
-    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, String> {
+    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, SchematicError> {
         self.ensure_region_loaded()?;
 
         let region = match &mut self.current_region {
@@ -297,19 +302,89 @@ impl<R: std::io::Read> SchematicInputStream for LitematicaSchematicInputStream<R
         Ok(Some(written))
     }
 
-    fn boundary(&mut self) -> Result<Option<Boundary>, String> {
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
         self.ensure_region_loaded()?;
         match &self.current_region {
             Some(r) => {
+                // emitted positions are origin + local with local < size, so this covers all of them
                 let (w, h, d) = r.size;
                 Ok(Some(Boundary::new(
                     r.origin.0, r.origin.1, r.origin.2,
-                    r.origin.0 + w as i32,
-                    r.origin.1 + h as i32,
-                    r.origin.2 + d as i32
+                    w as i32, h as i32, d as i32,
                 )))
             },
             None => Ok(None),
         }
     }
-}
\ No newline at end of file
+
+    fn format(&self) -> Option<SchematicFormat> {
+        Some(SchematicFormat::Litematica)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Boundary;
+    use crate::stream::litematic_bit_array::LitematicaBitArray;
+    use crate::stream::litematic_reader::LitematicaSchematicInputStream;
+    use crate::stream::stream::SchematicInputStream;
+    use fastnbt::{LongArray, Value};
+    use std::collections::HashMap;
+
+    fn xyz(x: i32, y: i32, z: i32) -> Value {
+        Value::Compound(HashMap::from([
+            ("x".to_string(), Value::Int(x)),
+            ("y".to_string(), Value::Int(y)),
+            ("z".to_string(), Value::Int(z)),
+        ]))
+    }
+
+    fn palette_entry(name: &str) -> Value {
+        Value::Compound(HashMap::from([("Name".to_string(), Value::String(name.to_string()))]))
+    }
+
+    #[test]
+    fn test_blocks_stay_within_boundary() {
+        let (w, h, d) = (3, 2, 4);
+        let mut bits = LitematicaBitArray::new(w * h * d, 2).unwrap();
+        for i in 0..w * h * d {
+            bits.set(i, 1 + (i % 2) as u64).unwrap();
+        }
+        let region = HashMap::from([
+            ("Position".to_string(), xyz(10, -5, 3)),
+            ("Size".to_string(), xyz(w as i32, h as i32, d as i32)),
+            ("BlockStatePalette".to_string(), Value::List(vec![
+                palette_entry("minecraft:air"),
+                palette_entry("minecraft:stone"),
+                palette_entry("minecraft:dirt"),
+            ])),
+            ("BlockStates".to_string(), Value::LongArray(LongArray::new(bits.to_nbt_vec()))),
+        ]);
+        let regions = HashMap::from([("main".to_string(), Value::Compound(region))]);
+        let root = Value::Compound(HashMap::from([("Regions".to_string(), Value::Compound(regions))]));
+        let bytes = fastnbt::to_bytes(&root).unwrap();
+
+        let mut reader = LitematicaSchematicInputStream::new(bytes.as_slice());
+        let boundary = reader.boundary().unwrap().unwrap();
+        assert_eq!(boundary, Boundary::new(10, -5, 3, 3, 2, 4));
+        let blocks = reader.read_to_end_into_vec().unwrap();
+        assert_eq!(blocks.len(), boundary.volume());
+        for block in &blocks {
+            assert!(boundary.contains(&block.position), "{:?} is outside {:?}", block.position, boundary);
+        }
+    }
+    #[test]
+    fn test_unrepresentable_size_is_rejected() {
+        let region = HashMap::from([
+            ("Position".to_string(), xyz(0, 0, 0)),
+            ("Size".to_string(), xyz(2, i32::MIN, 1)),
+            ("BlockStatePalette".to_string(), Value::List(vec![palette_entry("minecraft:air")])),
+        ]);
+        let regions = HashMap::from([("main".to_string(), Value::Compound(region))]);
+        let root = Value::Compound(HashMap::from([("Regions".to_string(), Value::Compound(regions))]));
+        let bytes = fastnbt::to_bytes(&root).unwrap();
+
+        let error = LitematicaSchematicInputStream::new(bytes.as_slice()).boundary().unwrap_err().to_string();
+        assert!(error.contains("must not be negative"), "Unexpected error: {}", error);
+    }
+}
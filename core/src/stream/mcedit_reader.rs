@@ -1,7 +1,10 @@
-use crate::common::{AxisOrder, Block, BlockState, Boundary, Region};
+use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
 use crate::store::blockstore::{BlockStore, PagedBlockStore};
 use crate::stream::legacy_ids::{convert_legacy_data_to_modern_properties, get_legacy_type};
-use crate::stream::stream::SchematicInputStream;
+use crate::stream::sponge_reader::read_var_int_array;
+use crate::stream::error::SchematicError;
+use crate::stream::format::SchematicFormat;
+use crate::stream::stream::{check_volume, checked_seek_index, SchematicInputStream, DEFAULT_MAX_VOLUME};
 use fastnbt::Value;
 use std::collections::HashMap;
 use std::io::Read;
@@ -13,6 +16,9 @@ pub struct MCEditSchematicInputStream<R: Read> {
     blocks: Option<Box<dyn BlockStore>>,
     read_blocks: usize,
     boundary: Option<Boundary>,
+    offset: BlockPosition,
+    max_volume: usize,
+    keep_unknown: bool,
 }
 
 impl<R: Read> MCEditSchematicInputStream<R> {
@@ -23,9 +29,22 @@ impl<R: Read> MCEditSchematicInputStream<R> {
             blocks: None,
             read_blocks: 0,
             boundary: None,
+            offset: BlockPosition::new(0, 0, 0),
+            max_volume: DEFAULT_MAX_VOLUME,
+            keep_unknown: false,
         }
     }
 
+    /// Keeps unrecognized legacy blocks as `minecraft:unknown[id=N,data=M]` placeholders instead of air.
+    pub fn set_keep_unknown(&mut self, keep_unknown: bool) {
+        self.keep_unknown = keep_unknown;
+    }
+
+    /// Sets the largest declared volume the reader accepts, defaults to `DEFAULT_MAX_VOLUME`.
+    pub fn set_max_volume(&mut self, max_volume: usize) {
+        self.max_volume = max_volume;
+    }
+
     fn read_nbt(&mut self) -> Result<(), String> {
         if self.header_read {
             return Err("MCEdit: NBT header has already been read".to_string());
@@ -38,20 +57,60 @@ impl<R: Read> MCEditSchematicInputStream<R> {
 
         if let Value::Compound(root) = result {
             let width = if let Some(Value::Short(w)) = root.get("Width") {
-                *w as usize
+                *w as i32
             } else {
                 return Err("MCEdit: Missing or invalid 'Width' tag".to_string());
             };
             let height = if let Some(Value::Short(h)) = root.get("Height") {
-                *h as usize
+                *h as i32
             } else {
                 return Err("MCEdit: Missing or invalid 'Height' tag".to_string());
             };
             let length = if let Some(Value::Short(l)) = root.get("Length") {
-                *l as usize
+                *l as i32
             } else {
                 return Err("MCEdit: Missing or invalid 'Length' tag".to_string());
             };
+
+            // WorldEdit stores the offset either as a single int array or as three separate ints.
+            // 'Materials' is informational only ("Alpha" for everything we can read), so it is ignored.
+            self.offset = match root.get("WEOffset") {
+                Some(Value::IntArray(offset)) if offset.len() == 3 => {
+                    BlockPosition::new(offset[0], offset[1], offset[2])
+                }
+                Some(_) => return Err("MCEdit: 'WEOffset' must be an int array of length 3".to_string()),
+                None => match (root.get("WEOffsetX"), root.get("WEOffsetY"), root.get("WEOffsetZ")) {
+                    (Some(Value::Int(x)), Some(Value::Int(y)), Some(Value::Int(z))) => BlockPosition::new(*x, *y, *z),
+                    _ => BlockPosition::new(0, 0, 0),
+                },
+            };
+
+            // blocks are stored relative to the schematic, the offset is only applied when reading
+            let boundary = Boundary::try_new(
+                self.offset.x(), self.offset.y(), self.offset.z(),
+                width, height, length,
+            ).map_err(|e| format!("MCEdit: {}", e))?;
+            check_volume(width as usize, height as usize, length as usize, self.max_volume)
+                .map_err(|e| format!("MCEdit: {}", e))?;
+            let local_boundary = Boundary::new_from_size(width, height, length);
+            self.boundary = Some(boundary);
+            self.blocks = Some(Box::new(
+                PagedBlockStore::builder()
+                    .boundary(local_boundary)
+                    .fixed(true)
+                    .capacity_hint(local_boundary.volume())
+                    .build(),
+            ));
+
+            if local_boundary.volume() == 0 {
+                return Err("MCEdit: Boundary volume is zero, cannot read blocks".to_string());
+            }
+
+            // FastAsyncWorldEdit writes .schematic files with a Sponge-style palette and varint block data
+            if root.contains_key("Palette") && root.contains_key("BlockData") {
+                return self.read_palette_block_data(&root, local_boundary);
+            }
+
             let blocks = if let Some(Value::ByteArray(blocks)) = root.get("Blocks") {
                 blocks.clone()
             } else {
@@ -79,13 +138,6 @@ impl<R: Read> MCEditSchematicInputStream<R> {
                 None
             };
 
-            self.boundary = Some(Boundary::new_from_size(width as i32, height as i32, length as i32));
-            self.blocks = Some(Box::new(PagedBlockStore::new_for_fixed_boundary(self.boundary.unwrap().clone())));
-
-            if self.boundary.unwrap().volume() == 0 {
-                return Err("MCEdit: Boundary volume is zero, cannot read blocks".to_string());
-            }
-
             let block_store = self.blocks.as_mut().unwrap();
 
             // "blocks" to u8 array, then use read_block_id to get the block id for each position in the boundary
@@ -98,11 +150,11 @@ impl<R: Read> MCEditSchematicInputStream<R> {
             if block_ids.len() != block_data.len() {
                 return Err("MCEdit: 'Blocks' and 'Data' arrays must be the same length".to_string());
             }
-            if block_ids.len() != self.boundary.unwrap().volume() as usize {
-                return Err(format!("MCEdit: 'Blocks' array length {} does not match expected volume {}", block_ids.len(), self.boundary.unwrap().volume()));
+            if block_ids.len() != local_boundary.volume() {
+                return Err(format!("MCEdit: 'Blocks' array length {} does not match expected volume {}", block_ids.len(), local_boundary.volume()));
             }
             let mut idx: usize = 0;
-            for position in self.boundary.unwrap().iter(AxisOrder::YZX) {
+            for position in local_boundary.iter(AxisOrder::YZX) {
                 if block_ids.len() <= idx || block_data.len() <= idx {
                     return Err(format!("MCEdit: Not enough block data for position {:?} at index {}", position, idx));
                 }
@@ -124,8 +176,13 @@ impl<R: Read> MCEditSchematicInputStream<R> {
                                 println!("MCEdit: Converted legacy block ID {} with data {} to modern state {:?}", block_id, block_data, state);
                                 block_state_cache.insert(block_cache_key, Rc::new(state));
                             }).unwrap_or_else(|| {
-                               println!("MCEdit: Warning - Unrecognized block ID {} with data {}, treating as air", block_id, block_data);
-                                block_state_cache.insert(block_cache_key, Rc::new(BlockState::air()));
+                                let state = if self.keep_unknown {
+                                    Self::unknown_placeholder(block_id, block_data)
+                                } else {
+                                    println!("MCEdit: Warning - Unrecognized block ID {} with data {}, treating as air", block_id, block_data);
+                                    BlockState::air()
+                                };
+                                block_state_cache.insert(block_cache_key, Rc::new(state));
                             });
                         }
                     }
@@ -140,6 +197,49 @@ impl<R: Read> MCEditSchematicInputStream<R> {
         Ok(())
     }
 
+    fn read_palette_block_data(&mut self, root: &HashMap<String, Value>, local_boundary: Boundary) -> Result<(), String> {
+        let palette = match root.get("Palette") {
+            Some(Value::Compound(palette)) => palette,
+            _ => return Err("MCEdit: 'Palette' tag is not a Compound".to_string()),
+        };
+        let mut states: HashMap<i32, Rc<BlockState>> = HashMap::new();
+        for (name, index) in palette {
+            let index = match index {
+                Value::Int(index) => *index,
+                _ => return Err("MCEdit: Palette entry value is not an Int".to_string()),
+            };
+            states.insert(index, Rc::new(BlockState::from_string(name.clone())?));
+        }
+        let block_data = match root.get("BlockData") {
+            Some(Value::ByteArray(data)) => data,
+            _ => return Err("MCEdit: 'BlockData' tag is not a ByteArray".to_string()),
+        };
+        let indices = read_var_int_array(block_data).map_err(|e| format!("MCEdit: {}", e))?;
+        if indices.len() != local_boundary.volume() {
+            return Err(format!("MCEdit: 'BlockData' length {} does not match expected volume {}", indices.len(), local_boundary.volume()));
+        }
+
+        let block_store = self.blocks.as_mut().unwrap();
+        for (position, index) in local_boundary.iter(AxisOrder::YZX).zip(indices) {
+            let state = states.get(&index)
+                .ok_or_else(|| format!("MCEdit: Palette index {} at {:?} is not in the palette", index, position))?;
+            if !state.is_air() {
+                block_store.set_block_at(&position, state.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unknown_placeholder(block_id: i32, block_data: u8) -> BlockState {
+        BlockState::new(
+            "minecraft:unknown".to_string(),
+            vec![
+                ("id".to_string(), block_id.to_string()),
+                ("data".to_string(), block_data.to_string()),
+            ],
+        )
+    }
+
     #[inline]
     fn read_block_id(block_ids: &[u8], idx: usize, add_blocks: Option<&[u8]>) -> i32 {
         let mut id = block_ids[idx] as i32 & 0xFF;
@@ -158,7 +258,7 @@ impl<R: Read> MCEditSchematicInputStream<R> {
 
 
 impl<R: Read> SchematicInputStream for MCEditSchematicInputStream<R> {
-    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, String> {
+    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, SchematicError> {
         if !self.header_read {
             self.read_nbt()?;
             self.header_read = true;
@@ -167,9 +267,8 @@ impl<R: Read> SchematicInputStream for MCEditSchematicInputStream<R> {
             return Err("MCEdit: Header not properly read".into());
         }
         let mut blocks_written = 0;
-        let boundary = self.boundary.unwrap();
         let blocks_store = self.blocks.as_ref().unwrap();
-        let mut block_iter = boundary.iter(AxisOrder::XYZ).skip(self.read_blocks);
+        let mut block_iter = blocks_store.iter(AxisOrder::XYZ).skip(self.read_blocks);
         while blocks_written < length {
             let pos = match block_iter.next() {
                 Some(p) => p,
@@ -180,8 +279,13 @@ impl<R: Read> SchematicInputStream for MCEditSchematicInputStream<R> {
                 Some(block_state) => {
                     if !block_state.is_air() {
                         let block = Block {
-                            position: pos,
+                            position: BlockPosition::new(
+                                pos.x() + self.offset.x(),
+                                pos.y() + self.offset.y(),
+                                pos.z() + self.offset.z(),
+                            ),
                             state: Rc::clone(&block_state),
+                            biome: None,
                         };
                         buffer.push(block);
                         blocks_written += 1;
@@ -197,20 +301,32 @@ impl<R: Read> SchematicInputStream for MCEditSchematicInputStream<R> {
         }
     }
 
-    fn boundary(&mut self) -> Result<Option<Boundary>, String> {
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
         if !self.header_read {
             self.read_nbt()?;
             self.header_read = true;
         }
         Ok(self.boundary.clone())
     }
+
+    fn seek_to(&mut self, block_index: usize) -> Result<(), SchematicError> {
+        self.read_blocks = checked_seek_index(self, "MCEdit", block_index)?;
+        Ok(())
+    }
+
+    fn format(&self) -> Option<SchematicFormat> {
+        Some(SchematicFormat::McEdit)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::common::{BlockPosition, BlockState, Boundary};
     use crate::stream::mcedit_reader::MCEditSchematicInputStream;
     use crate::stream::stream::SchematicInputStream;
+    use fastnbt::{ByteArray, IntArray, Value};
     use flate2::read::GzDecoder;
+    use std::collections::HashMap;
 
     #[test]
     fn test_mcedit_arbitrary_read() {
@@ -225,4 +341,82 @@ mod tests {
         // }
         // panic!("Abc")
     }
+
+    #[test]
+    fn test_mcedit_we_offset() {
+        let mut root = HashMap::new();
+        root.insert("Width".to_string(), Value::Short(2));
+        root.insert("Height".to_string(), Value::Short(1));
+        root.insert("Length".to_string(), Value::Short(1));
+        root.insert("Materials".to_string(), Value::String("Alpha".to_string()));
+        root.insert("Blocks".to_string(), Value::ByteArray(ByteArray::new(vec![0, 1])));
+        root.insert("Data".to_string(), Value::ByteArray(ByteArray::new(vec![0, 0])));
+        root.insert("WEOffset".to_string(), Value::IntArray(IntArray::new(vec![-3, 4, 5])));
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+        let mut reader = MCEditSchematicInputStream::new(bytes.as_slice());
+        assert_eq!(reader.boundary().unwrap(), Some(Boundary::new(-3, 4, 5, 2, 1, 1)));
+        let blocks = reader.read_to_end_into_vec().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].position, BlockPosition::new(-2, 4, 5));
+        assert_eq!(*blocks[0].state, BlockState::from_str("minecraft:stone").unwrap());
+    }
+
+    #[test]
+    fn test_mcedit_negative_size_is_rejected() {
+        let mut root = HashMap::new();
+        root.insert("Width".to_string(), Value::Short(-2));
+        root.insert("Height".to_string(), Value::Short(1));
+        root.insert("Length".to_string(), Value::Short(1));
+        root.insert("Blocks".to_string(), Value::ByteArray(ByteArray::new(vec![])));
+        root.insert("Data".to_string(), Value::ByteArray(ByteArray::new(vec![])));
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+        let error = MCEditSchematicInputStream::new(bytes.as_slice()).boundary().unwrap_err().to_string();
+        assert!(error.contains("must not be negative"), "Unexpected error: {}", error);
+    }
+
+    #[test]
+    fn test_mcedit_keep_unknown() {
+        let mut root = HashMap::new();
+        root.insert("Width".to_string(), Value::Short(1));
+        root.insert("Height".to_string(), Value::Short(1));
+        root.insert("Length".to_string(), Value::Short(1));
+        root.insert("Materials".to_string(), Value::String("Alpha".to_string()));
+        root.insert("Blocks".to_string(), Value::ByteArray(ByteArray::new(vec![-6])));
+        root.insert("AddBlocks".to_string(), Value::ByteArray(ByteArray::new(vec![0x0F])));
+        root.insert("Data".to_string(), Value::ByteArray(ByteArray::new(vec![3])));
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+        let mut reader = MCEditSchematicInputStream::new(bytes.as_slice());
+        reader.set_keep_unknown(true);
+        let blocks = reader.read_to_end_into_vec().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].state.to_string(), "minecraft:unknown[data=3,id=4090]");
+
+        let mut reader = MCEditSchematicInputStream::new(bytes.as_slice());
+        assert!(reader.read_to_end_into_vec().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mcedit_fawe_palette_schematic() {
+        const FAWE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/fawe.schematic");
+        let mut reader = MCEditSchematicInputStream::new(GzDecoder::new(FAWE_SCHEMATIC));
+        assert_eq!(reader.boundary().unwrap(), Some(Boundary::new(0, 0, 0, 3, 2, 2)));
+        let blocks = reader.read_to_end_into_vec().unwrap();
+        assert_eq!(blocks.len(), 8);
+        let state_at = |x, y, z| {
+            blocks.iter()
+                .find(|b| b.position == BlockPosition::new(x, y, z))
+                .map(|b| b.state.to_string())
+        };
+        assert_eq!(state_at(0, 0, 0), Some("minecraft:stone".to_string()));
+        assert_eq!(state_at(2, 0, 1), Some("minecraft:stone".to_string()));
+        assert_eq!(state_at(1, 1, 0), Some("minecraft:oak_log[axis=y]".to_string()));
+        assert_eq!(
+            state_at(2, 1, 1),
+            Some("minecraft:oak_stairs[facing=east,half=bottom,shape=straight,waterlogged=false]".to_string())
+        );
+        assert_eq!(state_at(0, 1, 0), None);
+    }
 }
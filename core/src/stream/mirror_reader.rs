@@ -0,0 +1,90 @@
+use crate::common::{Axis, Block, BlockPosition, Boundary};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicInputStream;
+
+/// Flips block positions along one axis within the inner stream's boundary.
+/// Only positions change; block states are passed through as-is.
+pub struct MirrorInputStream {
+    inner: Box<dyn SchematicInputStream>,
+    axis: Axis,
+    boundary: Option<Boundary>,
+}
+
+impl MirrorInputStream {
+    pub fn new(inner: Box<dyn SchematicInputStream>, axis: Axis) -> Self {
+        MirrorInputStream {
+            inner,
+            axis,
+            boundary: None,
+        }
+    }
+
+    fn mirror(&self, pos: BlockPosition, boundary: &Boundary) -> BlockPosition {
+        match self.axis {
+            Axis::X => BlockPosition::new(boundary.min_x + boundary.max_x() - pos.x(), pos.y(), pos.z()),
+            Axis::Y => BlockPosition::new(pos.x(), boundary.min_y + boundary.max_y() - pos.y(), pos.z()),
+            Axis::Z => BlockPosition::new(pos.x(), pos.y(), boundary.min_z + boundary.max_z() - pos.z()),
+        }
+    }
+}
+
+impl SchematicInputStream for MirrorInputStream {
+    fn read(&mut self, buffer: &mut Vec<Block>, offset: usize, length: usize) -> Result<Option<usize>, SchematicError> {
+        let boundary = match self.boundary()? {
+            Some(boundary) => boundary,
+            None => return Err("Mirror: Inner stream has no boundary to mirror within".into()),
+        };
+        let start = buffer.len();
+        let read = self.inner.read(buffer, offset, length)?;
+        for block in &mut buffer[start..] {
+            block.position = self.mirror(block.position, &boundary);
+        }
+        Ok(read)
+    }
+
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
+        if self.boundary.is_none() {
+            self.boundary = self.inner.boundary()?;
+        }
+        Ok(self.boundary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Axis, AxisOrder, Block, BlockPosition, BlockState, Boundary};
+    use crate::stream::mirror_reader::MirrorInputStream;
+    use crate::stream::stream::{SchematicInputStream, SchematicOutputStream};
+    use crate::stream::vxl_reader::VXLSchematicInputStream;
+    use crate::stream::vxl_writer::VXLSchematicOutputStream;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_mirror_along_x() {
+        let boundary = Boundary::new(10, 5, 5, 4, 1, 1);
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:glass", "minecraft:sand"];
+        let blocks: Vec<Block> = names.iter().enumerate()
+            .map(|(i, name)| Block::new(
+                Rc::new(BlockState::from_str(name).unwrap()),
+                BlockPosition::new(10 + i as i32, 5, 5),
+            ))
+            .collect();
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, AxisOrder::XYZ, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let inner = VXLSchematicInputStream::new(Cursor::new(data));
+        let mut mirror = MirrorInputStream::new(Box::new(inner), Axis::X);
+        assert_eq!(mirror.boundary().unwrap(), Some(boundary));
+        let mirrored = mirror.read_to_end_into_vec().unwrap();
+        assert_eq!(mirrored.len(), 4);
+        for (original, flipped) in blocks.iter().zip(&mirrored) {
+            assert_eq!(original.state, flipped.state);
+            assert_eq!(flipped.position.x(), 13 - (original.position.x() - 10));
+            assert_eq!(flipped.position.y(), original.position.y());
+            assert_eq!(flipped.position.z(), original.position.z());
+        }
+    }
+}
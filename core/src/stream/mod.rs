@@ -5,11 +5,29 @@ pub mod vxl_writer;
 pub mod vxl_reader;
 pub mod sponge_reader;
 pub mod sponge_writer;
+pub mod mcedit_reader;
 pub mod stream;
+pub mod error;
+pub mod filter_writer;
+pub mod csv_writer;
+pub mod json_lines_writer;
+pub mod chunked_writer;
+pub mod diff;
+pub mod mirror_reader;
+pub mod tee_writer;
+pub mod dedup_writer;
+pub mod compression;
+pub mod hashing_writer;
+pub mod shell_reader;
+pub mod format;
+pub mod debug_text_writer;
+pub mod bounds_writer;
+pub mod file;
 mod litematic_reader;
 mod litematic_bit_array;
 mod shared_stream;
-mod mcedit_reader;
 mod legacy_ids;
+mod varint;
+mod nbt_stream;
 
-
+pub use file::{open_file, save_file};
@@ -1,6 +1,9 @@
 use crate::common::{AxisOrder, Block, BlockState, Boundary, Region};
 use crate::store::blockstore::LazyPaletteBlockStoreWrapper;
-use crate::stream::stream::SchematicInputStream;
+use crate::stream::error::SchematicError;
+use crate::stream::format::SchematicFormat;
+use crate::stream::nbt_stream::skip_to_container_end;
+use crate::stream::stream::{check_volume, SchematicInputStream, DEFAULT_MAX_VOLUME};
 use fastnbt::stream::{Parser, Value};
 use fastnbt::Tag;
 use std::collections::HashMap;
@@ -11,6 +14,7 @@ pub struct MojangSchematicInputStream<R: std::io::Read> {
     size: (usize, usize, usize),
     header_read: bool,
     lazy_palette: LazyPalette,
+    max_volume: usize,
 }
 
 pub struct LazyPalette {
@@ -28,9 +32,15 @@ impl<R: std::io::Read> MojangSchematicInputStream<R> {
                 blocks: None,
                 current_index: 0,
             },
+            max_volume: DEFAULT_MAX_VOLUME,
         }
     }
 
+    /// Sets the largest declared volume the reader accepts, defaults to `DEFAULT_MAX_VOLUME`.
+    pub fn set_max_volume(&mut self, max_volume: usize) {
+        self.max_volume = max_volume;
+    }
+
     fn ensure_header_read(&mut self) -> Result<(), String> {
         if !self.header_read {
             self.header_read = true;
@@ -41,7 +51,7 @@ impl<R: std::io::Read> MojangSchematicInputStream<R> {
 }
 
 impl<R: std::io::Read> SchematicInputStream for MojangSchematicInputStream<R> {
-    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, String> {
+    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, SchematicError> {
         self.ensure_header_read()?;
 
         let wrapper = match &self.lazy_palette.blocks {
@@ -73,7 +83,7 @@ impl<R: std::io::Read> SchematicInputStream for MojangSchematicInputStream<R> {
         Ok(Some(written_count))
     }
 
-    fn boundary(&mut self) -> Result<Option<Boundary>, String> {
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
         self.ensure_header_read()?;
         let (x, y, z) = self.size;
 
@@ -83,6 +93,10 @@ impl<R: std::io::Read> SchematicInputStream for MojangSchematicInputStream<R> {
             Ok(None)
         }
     }
+
+    fn format(&self) -> Option<SchematicFormat> {
+        Some(SchematicFormat::Mojang)
+    }
 }
 
 impl<R: std::io::Read> MojangSchematicInputStream<R> {
@@ -90,11 +104,28 @@ impl<R: std::io::Read> MojangSchematicInputStream<R> {
         let mut palette_found = false;
         let mut blocks_found = false;
         let mut size_found = false;
+        let mut packed_blocks = None;
+        let mut in_root = false;
         loop {
             match self.parser.next() {
+                Ok(Value::Compound(_)) if !in_root => in_root = true,
+                // nothing we read lives in a nested compound, so e.g. metadata may contain anything
+                Ok(Value::Compound(_)) => skip_to_container_end(&mut self.parser)?,
+                // some exporters store a dense grid of palette indices instead of a block list
+                Ok(Value::ByteArray(Some(name), data)) if name.eq_ignore_ascii_case("blocks") => {
+                    packed_blocks = Some(data.iter().map(|&b| b as u8 as i32).collect::<Vec<_>>());
+                    blocks_found = true;
+                }
+                Ok(Value::IntArray(Some(name), data)) if name.eq_ignore_ascii_case("blocks") => {
+                    packed_blocks = Some(data);
+                    blocks_found = true;
+                }
                 Ok(Value::List(Some(name), tag, len)) => match name.to_lowercase().as_str() {
                     "size" if tag == Tag::Int && len == 3 => {
-                        self.size = poll_size(&mut self.parser)?;
+                        let (x, y, z) = poll_size(&mut self.parser)?;
+                        let size = Boundary::try_new(0, 0, 0, x, y, z).map_err(|e| format!("Mojang: {}", e))?;
+                        self.size = (size.d_x as usize, size.d_y as usize, size.d_z as usize);
+                        check_volume(self.size.0, self.size.1, self.size.2, self.max_volume).map_err(|e| format!("Mojang: {}", e))?;
                         size_found = true;
                     }
                     "palette" if tag == Tag::Compound => {
@@ -102,12 +133,21 @@ impl<R: std::io::Read> MojangSchematicInputStream<R> {
                         self.extract_palette_from_nbt_stream()?;
                         palette_found = true;
                     }
+                    // structure block files may carry several palette variants, we always use the first one
+                    "palettes" if tag == Tag::List => {
+                        self.ensure_blocks_initialized();
+                        self.extract_first_palette_variant_from_nbt_stream(len)?;
+                        palette_found = true;
+                    }
+                    "entities" => {
+                        skip_to_container_end(&mut self.parser)?;
+                    }
                     "blocks" if tag == Tag::Compound => {
                         self.ensure_blocks_initialized();
                         self.read_blocks_from_nbt_stream()?;
                         blocks_found = true;
                     }
-                    _ => {}
+                    _ => skip_to_container_end(&mut self.parser)?,
                 },
                 Ok(Value::CompoundEnd) | Ok(Value::ListEnd) => continue,
                 Ok(_) => {}
@@ -124,6 +164,9 @@ impl<R: std::io::Read> MojangSchematicInputStream<R> {
         if !blocks_found {
             return Err("Mojang: Blocks not found in header".into());
         }
+        if let Some(indices) = packed_blocks {
+            self.read_packed_blocks(&indices)?;
+        }
         if self.lazy_palette.blocks.is_none() {
             return Err("Mojang: Blocks not initialized after header parsing".into());
         }
@@ -169,44 +212,328 @@ impl<R: std::io::Read> MojangSchematicInputStream<R> {
         Ok(())
     }
 
-    fn read_blocks_from_nbt_stream(&mut self) -> Result<(), String> {
-        let mut coords = [0usize; 3];
-        let mut coord_idx = 0;
-        let mut depth = 1;
+    fn extract_first_palette_variant_from_nbt_stream(&mut self, variants: i32) -> Result<(), String> {
+        if variants <= 0 {
+            return Err("Mojang: 'palettes' does not contain any palette".into());
+        }
+        match self.parser.next().map_err(|e| e.to_string())? {
+            Value::List(None, Tag::Compound, _) => self.extract_palette_from_nbt_stream()?,
+            _ => return Err("Mojang: 'palettes' entry is not a list of compounds".into()),
+        }
+        skip_to_container_end(&mut self.parser)
+    }
 
-        while depth > 0 {
+    /// Reads the `blocks` list entry by entry. Anything besides `pos` and `state`, such as a
+    /// block entity's `nbt` compound, is skipped as a whole so its contents cannot be mistaken for block data.
+    fn read_blocks_from_nbt_stream(&mut self) -> Result<(), String> {
+        let mut pos = None;
+        let mut state = None;
+        loop {
             match self.parser.next().map_err(|e| e.to_string())? {
-                Value::List(_, Tag::Int, 3) => {
-                    depth += 1;
-                    coord_idx = 0;
+                Value::Compound(None) => {
+                    pos = None;
+                    state = None;
                 }
-                Value::Int(name, val) => match name {
-                    Some(ref n) if n == "state" => {
-                        if let Some(wrapper) = &mut self.lazy_palette.blocks {
-                            wrapper.set_unknown_block_at(coords[0] as i32, coords[1] as i32, coords[2] as i32, val as isize)?;
-                        }
-                    }
-                    None if coord_idx < 3 => {
-                        coords[coord_idx] = val as usize;
-                        coord_idx += 1;
+                Value::CompoundEnd => {
+                    if let (Some((x, y, z)), Some(state), Some(wrapper)) = (pos, state, &mut self.lazy_palette.blocks) {
+                        wrapper.set_unknown_block_at(x, y, z, state as isize)?;
                     }
-                    _ => {}
-                },
-                Value::ListEnd => depth -= 1,
+                }
+                Value::List(Some(name), Tag::Int, 3) if name == "pos" => {
+                    pos = Some(poll_size(&mut self.parser)?);
+                    skip_to_container_end(&mut self.parser)?;
+                }
+                Value::Int(Some(name), val) if name == "state" => state = Some(val),
+                Value::Compound(Some(_)) | Value::List(..) => skip_to_container_end(&mut self.parser)?,
+                Value::ListEnd => return Ok(()),
                 _ => {}
             }
         }
+    }
+}
+
+impl<R: std::io::Read> MojangSchematicInputStream<R> {
+    /// Places a dense grid of palette indices, one per cell of `size` in YZX order
+    /// (x fastest, then z, then y), as in Sponge block data.
+    fn read_packed_blocks(&mut self, indices: &[i32]) -> Result<(), String> {
+        let (x, y, z) = self.size;
+        let local_boundary = Boundary::new(0, 0, 0, x as i32, y as i32, z as i32);
+        if indices.len() != local_boundary.volume() {
+            return Err(format!(
+                "Mojang: Packed blocks have {} entries, but a {}x{}x{} size needs {}",
+                indices.len(), x, y, z, local_boundary.volume()
+            ));
+        }
+        let wrapper = self.lazy_palette.blocks.as_mut()
+            .ok_or_else(|| "Mojang: Blocks not initialized before packed blocks".to_string())?;
+        for (pos, &index) in local_boundary.iter(AxisOrder::YZX).zip(indices) {
+            wrapper.set_unknown_block_at(pos.x(), pos.y(), pos.z(), index as isize)?;
+        }
         Ok(())
     }
 }
 
-fn poll_size(reader: &mut Parser<impl std::io::Read>) -> Result<(usize, usize, usize), String> {
-    let mut dims = [0usize; 3];
-    for i in 0..3 {
+fn poll_size(reader: &mut Parser<impl std::io::Read>) -> Result<(i32, i32, i32), String> {
+    let mut dims = [0i32; 3];
+    for dim in dims.iter_mut() {
         match reader.next().map_err(|e| e.to_string())? {
-            Value::Int(_, val) => dims[i] = val as usize,
+            Value::Int(_, val) => *dim = val,
             _ => return Err("Expected 3 integers for Size".into()),
         }
     }
     Ok((dims[0], dims[1], dims[2]))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{BlockPosition, BlockState};
+    use crate::stream::mojang_reader::MojangSchematicInputStream;
+    use crate::stream::stream::SchematicInputStream;
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct PaletteEntry {
+        #[serde(rename = "Name")]
+        name: String,
+        #[serde(rename = "Properties", skip_serializing_if = "Option::is_none")]
+        properties: Option<HashMap<String, String>>,
+    }
+
+    #[derive(Serialize)]
+    struct BlockEntry {
+        pos: [i32; 3],
+        state: i32,
+    }
+
+    #[derive(Serialize)]
+    struct EntityNbt {
+        id: String,
+    }
+
+    #[derive(Serialize)]
+    struct EntityEntry {
+        #[serde(rename = "blockPos")]
+        block_pos: [i32; 3],
+        pos: [f64; 3],
+        nbt: EntityNbt,
+    }
+
+    #[derive(Serialize)]
+    struct MultiPaletteStructure {
+        #[serde(rename = "DataVersion")]
+        data_version: i32,
+        size: [i32; 3],
+        palettes: Vec<Vec<PaletteEntry>>,
+        entities: Vec<EntityEntry>,
+        blocks: Vec<BlockEntry>,
+    }
+
+    fn palette(names: &[&str]) -> Vec<PaletteEntry> {
+        names.iter()
+            .map(|name| PaletteEntry { name: name.to_string(), properties: None })
+            .collect()
+    }
+
+    #[test]
+    fn test_multi_palette_structure() {
+        let structure = MultiPaletteStructure {
+            data_version: 3465,
+            size: [2, 1, 1],
+            palettes: vec![
+                palette(&["minecraft:stone", "minecraft:oak_planks"]),
+                palette(&["minecraft:dirt", "minecraft:spruce_planks"]),
+            ],
+            entities: vec![EntityEntry {
+                block_pos: [0, 0, 0],
+                pos: [0.5, 0.0, 0.5],
+                nbt: EntityNbt { id: "minecraft:pig".to_string() },
+            }],
+            blocks: vec![
+                BlockEntry { pos: [0, 0, 0], state: 0 },
+                BlockEntry { pos: [1, 0, 0], state: 1 },
+            ],
+        };
+        let bytes = fastnbt::to_bytes(&structure).unwrap();
+        let mut reader = MojangSchematicInputStream::new(bytes.as_slice());
+        let blocks = reader.read_to_end_into_vec().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].position, BlockPosition::new(0, 0, 0));
+        assert_eq!(*blocks[0].state, BlockState::from_str("minecraft:stone").unwrap());
+        assert_eq!(blocks[1].position, BlockPosition::new(1, 0, 0));
+        assert_eq!(*blocks[1].state, BlockState::from_str("minecraft:oak_planks").unwrap());
+    }
+
+    #[test]
+    fn test_negative_size_is_rejected() {
+        let structure = MultiPaletteStructure {
+            data_version: 3465,
+            size: [2, -1, 1],
+            palettes: vec![palette(&["minecraft:stone"])],
+            entities: vec![],
+            blocks: vec![],
+        };
+        let bytes = fastnbt::to_bytes(&structure).unwrap();
+        let error = MojangSchematicInputStream::new(bytes.as_slice()).boundary().unwrap_err().to_string();
+        assert!(error.contains("must not be negative"), "Unexpected error: {}", error);
+    }
+
+    #[test]
+    fn test_block_entity_nbt_is_skipped() {
+        #[derive(Serialize)]
+        struct Item {
+            #[serde(rename = "Slot")]
+            slot: i8,
+            id: String,
+            // looks like block data on purpose
+            state: i32,
+            pos: [i32; 3],
+        }
+
+        #[derive(Serialize)]
+        struct ChestNbt {
+            id: String,
+            #[serde(rename = "Items")]
+            items: Vec<Item>,
+            #[serde(rename = "Lock")]
+            lock: Vec<String>,
+        }
+
+        #[derive(Serialize)]
+        struct BlockEntityEntry {
+            pos: [i32; 3],
+            state: i32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            nbt: Option<ChestNbt>,
+        }
+
+        #[derive(Serialize)]
+        struct Metadata {
+            size: [i32; 3],
+            blocks: Vec<i32>,
+        }
+
+        #[derive(Serialize)]
+        struct ChestStructure {
+            size: [i32; 3],
+            metadata: Metadata,
+            palette: Vec<PaletteEntry>,
+            blocks: Vec<BlockEntityEntry>,
+        }
+
+        let chest = |slot: i8| ChestNbt {
+            id: "minecraft:chest".to_string(),
+            items: vec![Item { slot, id: "minecraft:diamond".to_string(), state: 0, pos: [1, 1, 1] }],
+            lock: vec![],
+        };
+        let structure = ChestStructure {
+            size: [3, 1, 1],
+            metadata: Metadata { size: [9, 9, 9], blocks: vec![7] },
+            palette: palette(&["minecraft:chest", "minecraft:stone"]),
+            blocks: vec![
+                BlockEntityEntry { pos: [0, 0, 0], state: 0, nbt: Some(chest(0)) },
+                BlockEntityEntry { pos: [1, 0, 0], state: 1, nbt: None },
+                BlockEntityEntry { pos: [2, 0, 0], state: 0, nbt: Some(chest(5)) },
+            ],
+        };
+        let bytes = fastnbt::to_bytes(&structure).unwrap();
+        let mut reader = MojangSchematicInputStream::new(bytes.as_slice());
+        let blocks: Vec<(BlockPosition, String)> = reader.read_to_end_into_vec().unwrap()
+            .into_iter()
+            .map(|block| (block.position, block.state.to_string()))
+            .collect();
+        assert_eq!(blocks, vec![
+            (BlockPosition::new(0, 0, 0), "minecraft:chest".to_string()),
+            (BlockPosition::new(1, 0, 0), "minecraft:stone".to_string()),
+            (BlockPosition::new(2, 0, 0), "minecraft:chest".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_packed_block_grid() {
+        #[derive(Serialize)]
+        struct PackedStructure {
+            size: [i32; 3],
+            palette: Vec<PaletteEntry>,
+            blocks: fastnbt::ByteArray,
+        }
+        // 3x2x2, x fastest, then z, then y
+        let grid: [i8; 12] = [
+            1, 0, 2,
+            0, 0, 0,
+            0, 0, 0,
+            0, 0, 1,
+        ];
+        let structure = PackedStructure {
+            size: [3, 2, 2],
+            palette: palette(&["minecraft:air", "minecraft:stone", "minecraft:dirt"]),
+            blocks: fastnbt::ByteArray::new(grid.to_vec()),
+        };
+        let bytes = fastnbt::to_bytes(&structure).unwrap();
+        let mut reader = MojangSchematicInputStream::new(bytes.as_slice());
+        let mut blocks: Vec<(BlockPosition, String)> = reader.read_to_end_into_vec().unwrap()
+            .into_iter()
+            .map(|block| (block.position, block.state.to_string()))
+            .collect();
+        blocks.sort_by_key(|(pos, _)| (pos.y(), pos.z(), pos.x()));
+        assert_eq!(blocks, vec![
+            (BlockPosition::new(0, 0, 0), "minecraft:stone".to_string()),
+            (BlockPosition::new(2, 0, 0), "minecraft:dirt".to_string()),
+            (BlockPosition::new(2, 1, 1), "minecraft:stone".to_string()),
+        ]);
+
+        let structure = PackedStructure {
+            size: [3, 2, 3],
+            palette: palette(&["minecraft:air", "minecraft:stone", "minecraft:dirt"]),
+            blocks: fastnbt::ByteArray::new(grid.to_vec()),
+        };
+        let bytes = fastnbt::to_bytes(&structure).unwrap();
+        let mut reader = MojangSchematicInputStream::new(bytes.as_slice());
+        let error = reader.boundary().unwrap_err().to_string();
+        assert!(error.contains("Packed blocks have 12 entries"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn test_large_packed_structure() {
+        #[derive(Serialize)]
+        struct PackedStructure {
+            size: [i32; 3],
+            palette: Vec<PaletteEntry>,
+            blocks: fastnbt::IntArray,
+        }
+        let (sx, sy, sz) = (160, 48, 144);
+        let grid: Vec<i32> = (0..sx * sy * sz).map(|i| (i % 7 == 0) as i32).collect();
+        let structure = PackedStructure {
+            size: [sx, sy, sz],
+            palette: palette(&["minecraft:air", "minecraft:stone"]),
+            blocks: fastnbt::IntArray::new(grid.clone()),
+        };
+        let bytes = fastnbt::to_bytes(&structure).unwrap();
+        let mut reader = MojangSchematicInputStream::new(bytes.as_slice());
+        let blocks = reader.read_to_end_into_vec().unwrap();
+        assert_eq!(blocks.len(), grid.iter().filter(|&&index| index == 1).count());
+        for block in &blocks {
+            let pos = block.position;
+            assert_eq!(grid[((pos.y() * sz + pos.z()) * sx + pos.x()) as usize], 1, "{:?}", pos);
+        }
+    }
+
+    #[test]
+    fn test_oversized_declared_size_is_rejected() {
+        #[derive(Serialize)]
+        struct HugeStructure {
+            size: [i32; 3],
+            palette: Vec<PaletteEntry>,
+            blocks: Vec<BlockEntry>,
+        }
+        let structure = HugeStructure {
+            size: [100_000, 100_000, 100_000],
+            palette: vec![PaletteEntry { name: "minecraft:stone".to_string(), properties: None }],
+            blocks: vec![BlockEntry { pos: [0, 0, 0], state: 0 }],
+        };
+        let bytes = fastnbt::to_bytes(&structure).unwrap();
+        let mut reader = MojangSchematicInputStream::new(bytes.as_slice());
+        let error = reader.boundary().unwrap_err().to_string();
+        assert!(error.contains("maximum volume"), "unexpected error: {}", error);
+    }
+}
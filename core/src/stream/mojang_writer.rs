@@ -1,4 +1,6 @@
 use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
+use crate::store::blockstore::{BlockStore, PagedBlockStore};
+use crate::stream::error::SchematicError;
 use crate::stream::stream::SchematicOutputStream;
 use serde::Serialize;
 use std::collections::HashMap;
@@ -6,20 +8,18 @@ use std::rc::Rc;
 
 pub struct MojangSchematicOutputStream<W: std::io::Write> {
     writer: W,
-    block: HashMap<BlockPosition, BlockEntry>,
+    blocks: PagedBlockStore,
     palette: Vec<PaletteEntry>,
     palette_map: HashMap<Rc<BlockState>, i32>,
-    boundary: Boundary
 }
 
 impl<W: std::io::Write> MojangSchematicOutputStream<W> {
     pub fn new(writer: W) -> Self {
         Self {
             writer,
-            block: HashMap::new(),
+            blocks: PagedBlockStore::new_empty_resizable(),
             palette: Vec::new(),
             palette_map: HashMap::new(),
-            boundary: Boundary::new_empty(),
         }
     }
 
@@ -71,47 +71,169 @@ struct BlockEntry {
 }
 
 impl<W: std::io::Write> SchematicOutputStream for MojangSchematicOutputStream<W> {
-    fn write(&mut self, blocks: &[Block]) -> Result<usize, String> {
-        let mut block_count = 0;
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
         for block in blocks {
-            let block_state = block.state.clone();
-            let state_index = self.palette_idx_from_state(&block_state);
-            let block_position = block.position;
-            self.boundary = self.boundary.expand_to_include(&block_position);
-            self.block.insert(block_position, BlockEntry {
-                pos: block_position.to_array(),
-                state: state_index,
-            });
-            block_count += 1;
+            self.blocks.set_block_at(&block.position, block.state.clone())?;
         }
-        Ok(block_count)
+        Ok(blocks.len())
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) -> Result<(), SchematicError> {
+        let current = *self.blocks.boundary();
+        let merged = if current.volume() == 0 {
+            boundary
+        } else {
+            boundary
+                .expand_to_include(&BlockPosition::new(current.min_x, current.min_y, current.min_z))
+                .expand_to_include(&BlockPosition::new(current.max_x(), current.max_y(), current.max_z()))
+        };
+        self.blocks.set_boundary(merged);
+        Ok(())
     }
 
-    fn complete(&mut self) -> Result<(), String> {
-        let air_state_index = self.palette_idx_from_state(&BlockState::air_rc());
-        let mut full_block_list = Vec::new();
-        for pos in self.boundary.iter(AxisOrder::XYZ) {
-            let block_entry = self.block.get(&pos);
-            if let Some(entry) = block_entry {
-                full_block_list.push(entry.clone());
-            } else {
-                full_block_list.push(BlockEntry {
-                    pos: pos.to_array(),
-                    state: air_state_index,
-                });
-            }
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        let boundary = *self.blocks.boundary();
+        let air = BlockState::air_rc();
+        let mut full_block_list = Vec::with_capacity(boundary.volume());
+        for pos in boundary.iter(AxisOrder::XYZ) {
+            let state = self.blocks.block_at(&pos)?.unwrap_or_else(|| air.clone());
+            full_block_list.push(BlockEntry {
+                pos: pos.to_array(),
+                state: self.palette_idx_from_state(&state),
+            });
         }
         let structure = StructureData {
             data_version: 3465,
-            size: self.boundary.size_as_array(),
+            size: boundary.size_as_array(),
             palette: self.palette.clone(),
             blocks: full_block_list,
         };
         let result = match fastnbt::to_writer(&mut self.writer, &structure) {
             Ok(_) => Ok(()),
-            Err(e) => Err(format!("Failed to serialize NBT: {}", e)),
+            Err(e) => Err(SchematicError::MalformedNbt(format!("Failed to serialize NBT: {}", e))),
         };
         self.writer.flush().map_err(|e| format!("Failed to flush NBT: {}", e))?;
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary};
+    use crate::stream::mojang_reader::MojangSchematicInputStream;
+    use crate::stream::mojang_writer::MojangSchematicOutputStream;
+    use crate::stream::stream::{BlockTransform, SchematicInputStream, SchematicOutputStream, SharedBuffer};
+    use crate::stream::vxl_reader::VXLSchematicInputStream;
+    use crate::stream::vxl_writer::VXLSchematicOutputStream;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_transfer_vxl_into_mojang_keeps_boundary() {
+        let boundary = Boundary::new_from_size(4, 3, 5);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks = vec![
+            Block::new(stone.clone(), BlockPosition::new(0, 0, 0)),
+            Block::new(stone.clone(), BlockPosition::new(1, 1, 1)),
+        ];
+        let mut vxl = Vec::new();
+        let mut vxl_writer = VXLSchematicOutputStream::new(&mut vxl, AxisOrder::XYZ, boundary);
+        vxl_writer.write(&blocks).unwrap();
+        vxl_writer.complete().unwrap();
+
+        let mojang = Rc::new(RefCell::new(Vec::new()));
+        let mut vxl_reader = VXLSchematicInputStream::new(vxl.as_slice());
+        vxl_reader
+            .transfer_into(Box::new(MojangSchematicOutputStream::new(SharedBuffer(mojang.clone()))))
+            .unwrap();
+
+        let bytes = mojang.borrow().clone();
+        let mut mojang_reader = MojangSchematicInputStream::new(bytes.as_slice());
+        assert_eq!(mojang_reader.boundary().unwrap(), Some(boundary));
+        assert_eq!(mojang_reader.read_to_end_into_vec().unwrap(), blocks);
+    }
+
+    #[test]
+    fn test_transfer_with_translate_and_remap() {
+        let boundary = Boundary::new(5, 0, 0, 3, 2, 2);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        let blocks = vec![
+            Block::new(stone.clone(), BlockPosition::new(5, 0, 0)),
+            Block::new(dirt.clone(), BlockPosition::new(6, 1, 0)),
+            Block::new(stone.clone(), BlockPosition::new(7, 1, 1)),
+        ];
+        let mut vxl = Vec::new();
+        let mut vxl_writer = VXLSchematicOutputStream::new(&mut vxl, AxisOrder::XYZ, boundary);
+        vxl_writer.write(&blocks).unwrap();
+        vxl_writer.complete().unwrap();
+
+        let glass = Rc::new(BlockState::from_str("minecraft:glass").unwrap());
+        let translate: BlockTransform = Box::new(|blocks: Vec<Block>| {
+            blocks.into_iter()
+                .map(|block| {
+                    let pos = block.position;
+                    Block::new(block.state, BlockPosition::new(pos.x() - 5, pos.y(), pos.z()))
+                })
+                .collect()
+        });
+        let remap: BlockTransform = Box::new(move |blocks: Vec<Block>| {
+            blocks.into_iter()
+                .map(|block| match block.state.name_ref().as_str() {
+                    "minecraft:stone" => Block::new(glass.clone(), block.position),
+                    _ => block,
+                })
+                .collect()
+        });
+
+        let mojang = Rc::new(RefCell::new(Vec::new()));
+        let mut vxl_reader = VXLSchematicInputStream::new(vxl.as_slice());
+        vxl_reader
+            .transfer_with(
+                Box::new(MojangSchematicOutputStream::new(SharedBuffer(mojang.clone()))),
+                &[translate, remap],
+            )
+            .unwrap();
+
+        let bytes = mojang.borrow().clone();
+        let mut mojang_reader = MojangSchematicInputStream::new(bytes.as_slice());
+        assert_eq!(mojang_reader.boundary().unwrap(), Some(Boundary::new_from_size(3, 2, 2)));
+        let mut read_blocks: Vec<(String, [i32; 3])> = mojang_reader.read_to_end_into_vec().unwrap()
+            .iter()
+            .map(|block| (block.state.to_string(), block.position.to_array()))
+            .collect();
+        read_blocks.sort_by_key(|(_, pos)| *pos);
+        assert_eq!(read_blocks, vec![
+            ("minecraft:glass".to_string(), [0, 0, 0]),
+            ("minecraft:dirt".to_string(), [1, 1, 0]),
+            ("minecraft:glass".to_string(), [2, 1, 1]),
+        ]);
+    }
+
+    #[test]
+    fn test_sparse_64_cubed_build() {
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let glass = Rc::new(BlockState::from_str("minecraft:glass").unwrap());
+        let mut blocks = Vec::new();
+        for i in 0..64 {
+            blocks.push(Block::new(stone.clone(), BlockPosition::new(i, i, i)));
+            blocks.push(Block::new(glass.clone(), BlockPosition::new(i, 0, 63)));
+        }
+
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mut writer = MojangSchematicOutputStream::new(SharedBuffer(output.clone()));
+        writer.write(&blocks).unwrap();
+        // only the pages along the diagonal and the glass row are allocated, not the whole cube
+        let pages = writer.blocks.iter_pages().count();
+        assert!(pages > 0 && pages <= 16, "{} pages allocated", pages);
+        writer.complete().unwrap();
+
+        let bytes = output.borrow().clone();
+        let mut reader = MojangSchematicInputStream::new(bytes.as_slice());
+        assert_eq!(reader.boundary().unwrap(), Some(Boundary::new_from_size(64, 64, 64)));
+        let mut read_blocks = reader.read_to_end_into_vec().unwrap();
+        read_blocks.sort_by_key(|b| b.position.to_array());
+        blocks.sort_by_key(|b| b.position.to_array());
+        assert_eq!(read_blocks, blocks);
+    }
+}
@@ -0,0 +1,20 @@
+use fastnbt::stream::{Parser, Value};
+use std::io::Read;
+
+/// Consumes values until the list or compound the parser is currently in has ended,
+/// including that end tag itself.
+pub fn skip_to_container_end<R: Read>(parser: &mut Parser<R>) -> Result<(), String> {
+    let mut depth = 0;
+    loop {
+        match parser.next().map_err(|e| e.to_string())? {
+            Value::Compound(_) | Value::List(..) => depth += 1,
+            Value::CompoundEnd | Value::ListEnd => {
+                if depth == 0 {
+                    return Ok(());
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::common::{AxisOrder, Block, BlockPosition, Boundary, Region};
+use crate::store::blockstore::{BlockStore, PagedBlockStore};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicInputStream;
+
+const NEIGHBOR_OFFSETS: [(i32, i32, i32); 6] = [
+    (1, 0, 0), (-1, 0, 0),
+    (0, 1, 0), (0, -1, 0),
+    (0, 0, 1), (0, 0, -1),
+];
+
+/// Emits only the surface of the inner stream's volume: blocks with at least one empty or air
+/// face neighbor, so a solid build comes out hollow.
+/// Surface checks need random access, so the whole inner stream is buffered into a store on the first read.
+pub struct ShellInputStream {
+    inner: Box<dyn SchematicInputStream>,
+    surface: Option<std::vec::IntoIter<Block>>,
+}
+
+impl ShellInputStream {
+    pub fn new(inner: Box<dyn SchematicInputStream>) -> Self {
+        ShellInputStream { inner, surface: None }
+    }
+
+    fn load_surface(&mut self) -> Result<Vec<Block>, SchematicError> {
+        let mut store = match self.inner.boundary()? {
+            Some(boundary) => PagedBlockStore::new_for_fixed_boundary(boundary),
+            None => PagedBlockStore::new_empty_resizable(),
+        };
+        self.inner.read_to_end(&mut store)?;
+        let mut surface = Vec::new();
+        for (pos, state) in store.block_iterator(AxisOrder::XYZ) {
+            let Some(state) = state else { continue };
+            if Self::is_exposed(&store, &pos)? {
+                surface.push(Block::new(state, pos));
+            }
+        }
+        Ok(surface)
+    }
+
+    fn is_exposed(store: &PagedBlockStore, pos: &BlockPosition) -> Result<bool, SchematicError> {
+        for (dx, dy, dz) in NEIGHBOR_OFFSETS {
+            let neighbor = BlockPosition::new(pos.x() + dx, pos.y() + dy, pos.z() + dz);
+            if !store.contains(&neighbor) {
+                return Ok(true);
+            }
+            match store.block_at(&neighbor)? {
+                Some(state) if !state.is_air() => {}
+                _ => return Ok(true),
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl SchematicInputStream for ShellInputStream {
+    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, SchematicError> {
+        if self.surface.is_none() {
+            self.surface = Some(self.load_surface()?.into_iter());
+        }
+        let surface = self.surface.as_mut().unwrap();
+        let start = buffer.len();
+        buffer.extend(surface.by_ref().take(length));
+        let read = buffer.len() - start;
+        if read == 0 && length > 0 {
+            Ok(None)
+        } else {
+            Ok(Some(read))
+        }
+    }
+
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
+        self.inner.boundary()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{AxisOrder, Block, BlockState, Boundary, Region};
+    use crate::stream::shell_reader::ShellInputStream;
+    use crate::stream::stream::{SchematicInputStream, SchematicOutputStream};
+    use crate::stream::vxl_reader::VXLSchematicInputStream;
+    use crate::stream::vxl_writer::VXLSchematicOutputStream;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_solid_cube_is_hollowed() {
+        let boundary = Boundary::new_from_size(4, 4, 4);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ).map(|pos| Block::new(stone.clone(), pos)).collect();
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, AxisOrder::XYZ, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut shell = ShellInputStream::new(Box::new(VXLSchematicInputStream::new(Cursor::new(data))));
+        assert_eq!(shell.boundary().unwrap(), Some(boundary));
+        let surface = shell.read_to_end_into_vec().unwrap();
+        assert_eq!(surface.len(), 56);
+        let interior = Boundary::new(1, 1, 1, 2, 2, 2);
+        assert!(surface.iter().all(|block| !interior.contains(&block.position)));
+    }
+}
@@ -1,22 +1,45 @@
-use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
-use crate::store::blockstore::LazyPaletteBlockStoreWrapper;
-use crate::stream::stream::SchematicInputStream;
+use crate::common::{AxisOrder, Block, BlockPosition, BlockState, BlockStateRegistry, Boundary, Region};
+use crate::store::blockstore::{LazyPaletteBlockStoreWrapper, PagedBlockStore};
+use crate::stream::error::SchematicError;
+use crate::stream::legacy_ids::{convert_legacy_data_to_modern_properties, get_legacy_type};
+use crate::stream::format::SchematicFormat;
+use crate::stream::nbt_stream::skip_to_container_end;
+use crate::stream::stream::{check_volume, checked_seek_index, SchematicInputStream, DEFAULT_MAX_VOLUME};
+use crate::stream::varint::read_var_int;
+use fastnbt::stream::{Parser, Value as StreamValue};
 use fastnbt::Value;
 use std::collections::HashMap;
 use std::io::Read;
 use std::ops::Deref;
 use std::rc::Rc;
 
+/// The first data version after the 1.13 flattening. Older files may key their palette by
+/// numeric `id` or `id:data` instead of block state strings.
+const FLATTENING_DATA_VERSION: i32 = 1451;
+
+/// The biome of positions a biome container does not cover, and the one Sponge writers fill in
+/// for positions no written block carried a biome for.
+pub const DEFAULT_BIOME: &str = "minecraft:plains";
+
 pub struct SpongeSchematicInputStream<R: Read> {
     reader: R,
     header_read: bool,
     blocks: Option<LazyPaletteBlockStoreWrapper>,
     read_blocks: usize,
     boundary: Option<Boundary>,
+    intern_states: bool,
+    max_volume: usize,
+    include_air: bool,
+    read_order: AxisOrder,
+    offset: BlockPosition,
+    streaming: bool,
+    /// Biomes in YZX order, one per position (v3) or per x/z column (v2), if the schematic has any.
+    biomes: Option<Vec<Rc<str>>>,
+    default_biome: Rc<str>,
 }
 
 impl<R: Read> SchematicInputStream for SpongeSchematicInputStream<R> {
-    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, String> {
+    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, SchematicError> {
         if !self.header_read {
             self.read_header()?;
         }
@@ -24,21 +47,29 @@ impl<R: Read> SchematicInputStream for SpongeSchematicInputStream<R> {
             return Err("Sponge: Header not properly read".into());
         }
         let mut blocks_written = 0;
-        let boundary = self.boundary.unwrap();
         let blocks_store = self.blocks.as_ref().unwrap();
-        let mut block_iter = boundary.iter(AxisOrder::XYZ).skip(self.read_blocks);
+        let mut block_iter = blocks_store.iter(self.read_order).skip(self.read_blocks);
         while blocks_written < length {
             let pos = match block_iter.next() {
                 Some(p) => p,
                 None => break,
             };
-            match blocks_store.block_at(&pos)? {
+            let block_state = match blocks_store.block_at(&pos)? {
+                None if self.include_air => Some(BlockState::air_rc()),
+                state => state,
+            };
+            match block_state {
                 None => {}
                 Some(block_state) => {
-                    if !block_state.is_air() {
+                    if self.include_air || !block_state.is_air() {
                         let block = Block {
-                            position: pos,
+                            position: BlockPosition::new(
+                                pos.x() + self.offset.x(),
+                                pos.y() + self.offset.y(),
+                                pos.z() + self.offset.z(),
+                            ),
                             state: Rc::clone(&block_state),
+                            biome: self.biome_at(&pos),
                         };
                         buffer.push(block);
                         blocks_written += 1;
@@ -54,12 +85,21 @@ impl<R: Read> SchematicInputStream for SpongeSchematicInputStream<R> {
         }
     }
 
-    fn boundary(&mut self) -> Result<Option<Boundary>, String> {
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
         if !self.header_read {
             self.read_header()?;
         }
         Ok(self.boundary)
     }
+
+    fn seek_to(&mut self, block_index: usize) -> Result<(), SchematicError> {
+        self.read_blocks = checked_seek_index(self, "Sponge", block_index)?;
+        Ok(())
+    }
+
+    fn format(&self) -> Option<SchematicFormat> {
+        Some(SchematicFormat::Sponge)
+    }
 }
 
 
@@ -71,10 +111,54 @@ impl<R: Read> SpongeSchematicInputStream<R> {
             blocks: None,
             read_blocks: 0,
             boundary: None,
+            intern_states: false,
+            max_volume: DEFAULT_MAX_VOLUME,
+            include_air: false,
+            read_order: AxisOrder::XYZ,
+            offset: BlockPosition::new(0, 0, 0),
+            streaming: false,
+            biomes: None,
+            default_biome: Rc::from(DEFAULT_BIOME),
         }
     }
 
+    /// The order in which Sponge schematics lay out their block data: Y outermost, X innermost.
+    pub fn native_axis_order(&self) -> AxisOrder {
+        AxisOrder::YZX
+    }
+
+    /// Sets the order in which `read` emits positions, defaults to `AxisOrder::XYZ`.
+    /// Must be set before the first read.
+    pub fn set_read_order(&mut self, read_order: AxisOrder) {
+        self.read_order = read_order;
+    }
+
+    /// Emits air blocks as well, so every position of the boundary is read. Defaults to false.
+    pub fn set_include_air(&mut self, include_air: bool) {
+        self.include_air = include_air;
+    }
+
+    /// Parses the NBT tag by tag instead of building the whole tree first, so peak memory is the
+    /// block store plus the raw 'BlockData' array, about a byte per cell for small palettes.
+    /// Block entities and entities are skipped unread. Defaults to false.
+    pub fn set_streaming(&mut self, streaming: bool) {
+        self.streaming = streaming;
+    }
+
+    /// Sets the largest declared volume the reader accepts, defaults to `DEFAULT_MAX_VOLUME`.
+    pub fn set_max_volume(&mut self, max_volume: usize) {
+        self.max_volume = max_volume;
+    }
+
+    /// Shares palette states through the thread-local `BlockStateRegistry`.
+    pub fn set_intern_states(&mut self, intern_states: bool) {
+        self.intern_states = intern_states;
+    }
+
     fn read_header(&mut self) -> Result<(), String> {
+        if self.streaming {
+            return self.read_header_streaming();
+        }
         let result: Value = fastnbt::from_reader(&mut self.reader).map_err(|e| format!("Sponge: Failed to read NBT data: {}", e))?;
         if let Value::Compound(root) = result {
             let schematic_value = if root.contains_key("Schematic") {
@@ -96,12 +180,15 @@ impl<R: Read> SpongeSchematicInputStream<R> {
                     Some(Value::Short(v)) => *v as i32,
                     _ => return Err("Sponge: Missing or invalid 'Width' tag".into()),
                 };
-                self.boundary = Some(Boundary::new(0, 0, 0, width, height, length));
-                self.blocks = Some(LazyPaletteBlockStoreWrapper::empty_fixed_from_size(
-                    width as usize, height as usize, length as usize,
-                ));
+                let offset = match schematic.get("Offset") {
+                    Some(Value::IntArray(offset)) => Some(offset.to_vec()),
+                    Some(_) => return Err("Sponge: 'Offset' must be an int array of length 3".into()),
+                    None => None,
+                };
+                self.init_blocks(width, height, length, offset)?;
                 self.process_palette(schematic).map_err(|e| format!("Sponge: Failed to process palette: {}", e))?;
                 self.process_blocks(schematic).map_err(|e| format!("Sponge: Failed to process blocks: {}", e))?;
+                self.process_biomes(schematic).map_err(|e| format!("Sponge: Failed to process biomes: {}", e))?;
             } else {
                 return Err("Sponge: Missing or invalid 'Schematic' tag".into());
             }
@@ -112,11 +199,62 @@ impl<R: Read> SpongeSchematicInputStream<R> {
         }
     }
 
+    fn init_blocks(&mut self, width: i32, height: i32, length: i32, offset: Option<Vec<i32>>) -> Result<(), String> {
+        self.offset = match offset {
+            Some(offset) if offset.len() == 3 => BlockPosition::new(offset[0], offset[1], offset[2]),
+            Some(_) => return Err("Sponge: 'Offset' must be an int array of length 3".into()),
+            None => BlockPosition::new(0, 0, 0),
+        };
+        // blocks are stored relative to the schematic, the offset is only applied when reading
+        let boundary = Boundary::try_new(
+            self.offset.x(), self.offset.y(), self.offset.z(),
+            width, height, length,
+        ).map_err(|e| format!("Sponge: {}", e))?;
+        check_volume(width as usize, height as usize, length as usize, self.max_volume)
+            .map_err(|e| format!("Sponge: {}", e))?;
+        self.boundary = Some(boundary);
+        // Data is filled in YZX order, so lay the pages out the same way
+        let local_boundary = Boundary::new_from_size(width, height, length);
+        self.blocks = Some(LazyPaletteBlockStoreWrapper::from(Box::new(
+            PagedBlockStore::builder()
+                .boundary(local_boundary)
+                .fixed(true)
+                .axis_order(AxisOrder::YZX)
+                .capacity_hint(local_boundary.volume())
+                .build(),
+        )));
+        Ok(())
+    }
+
+    fn read_header_streaming(&mut self) -> Result<(), String> {
+        let header = StreamedHeader::parse(&mut Parser::new(&mut self.reader))
+            .map_err(|e| format!("Sponge: Failed to read NBT data: {}", e))?;
+        let (width, height, length) = match (header.width, header.height, header.length) {
+            (Some(width), Some(height), Some(length)) => (width as i32, height as i32, length as i32),
+            (None, _, _) => return Err("Sponge: Missing or invalid 'Width' tag".into()),
+            (_, None, _) => return Err("Sponge: Missing or invalid 'Height' tag".into()),
+            (_, _, None) => return Err("Sponge: Missing or invalid 'Length' tag".into()),
+        };
+        self.init_blocks(width, height, length, header.offset)?;
+        let palette = header.palette.ok_or_else(|| "Sponge: Missing 'Palette' tag".to_string())?;
+        self.set_palette(palette, header.data_version).map_err(|e| format!("Sponge: Failed to process palette: {}", e))?;
+        let data = header.data.ok_or_else(|| "Sponge: Missing 'BlockData' tag".to_string())?;
+        self.process_block_data(&data)
+            .map_err(|e| format!("Sponge: Failed to process blocks: {}", e))?;
+        match (header.biome_palette, header.biome_data) {
+            (None, None) => {}
+            (Some(palette), Some(data)) => self.set_biomes(palette, &data)
+                .map_err(|e| format!("Sponge: Failed to process biomes: {}", e))?,
+            _ => return Err("Sponge: Biomes need a 'Palette' compound and a 'Data' byte array".into()),
+        }
+        self.header_read = true;
+        Ok(())
+    }
+
     fn process_palette(&mut self, schematic: &HashMap<String, Value>) -> Result<(), String> {
         if self.blocks.is_none() {
             return Err("Sponge: Blocks store not initialized before processing palette".into());
         }
-        let blocks = self.blocks.as_mut().unwrap();
         let palette_tag = if schematic.contains_key("Blocks") {
             match &schematic["Blocks"] {
                 Value::Compound(content) => {
@@ -132,14 +270,37 @@ impl<R: Read> SpongeSchematicInputStream<R> {
             Value::Compound(map) => map,
             _ => return Err("Sponge: 'Palette' tag is not a Compound".into()),
         };
-        let mut palette: HashMap<isize, Rc<BlockState>> = HashMap::new();
-        for x in palette_compound {
-            let name = x.0;
-            let state = match &x.1 {
-                Value::Int(v) => *v,
+        let mut entries = Vec::with_capacity(palette_compound.len());
+        for (name, value) in palette_compound {
+            match value {
+                Value::Int(v) => entries.push((name.clone(), *v)),
                 _ => return Err("Sponge: Palette entry value is not an Int".into()),
+            }
+        }
+        let data_version = match schematic.get("DataVersion") {
+            Some(Value::Int(data_version)) => Some(*data_version),
+            _ => None,
+        };
+        self.set_palette(entries, data_version)
+    }
+
+    fn set_palette(&mut self, entries: Vec<(String, i32)>, data_version: Option<i32>) -> Result<(), String> {
+        let legacy = data_version.is_some_and(|data_version| data_version < FLATTENING_DATA_VERSION);
+        let blocks = match self.blocks.as_mut() {
+            Some(blocks) => blocks,
+            None => return Err("Sponge: Blocks store not initialized before processing palette".into()),
+        };
+        let mut palette: HashMap<isize, Rc<BlockState>> = HashMap::new();
+        for (name, state) in entries {
+            let block_state = match legacy_id(&name).filter(|_| legacy) {
+                Some((id, data)) => legacy_state(id, data)?,
+                None => BlockState::from_string(name)?,
+            };
+            let block_state = if self.intern_states {
+                BlockStateRegistry::intern(block_state)
+            } else {
+                Rc::new(block_state)
             };
-            let block_state = Rc::new(BlockState::from_string(name.clone())?);
             palette.insert(state as isize, block_state);
         }
         blocks.set_actual_palette(palette);
@@ -165,87 +326,280 @@ impl<R: Read> SpongeSchematicInputStream<R> {
             &schematic["BlockData"]
         };
         match block_tag {
-            Value::ByteArray(byte_array) => {
-                let bytes = byte_array.deref();
-                let bytes = bytes.iter().map(|b| *b as u8).collect::<Vec<u8>>();
-                let block_states = self.read_var_int_array(&bytes)?;
-                let boundary = self.boundary.unwrap();
-                let mut block_iter = boundary.iter(AxisOrder::YZX);
-                let blocks = self.blocks.as_mut().unwrap();
-                let air_state_index: i32 = blocks.state_to_temp_id(
-                    &BlockState::air_rc()
-                ).map(|t| t as i32).unwrap_or(-1);
-
-                let mut block_positions: [BlockPosition; 4096] = [BlockPosition::new(0, 0, 0); 4096];
-                let mut state_indices: [isize; 4096] = [0; 4096];
-                let mut pos_index = 0;
-
-                for (_, state_index) in block_states.iter().enumerate() {
-                    if *state_index < 0 {
-                        return Err(format!("Sponge: Invalid block state index {} in block data", state_index));
-                    }
-                    let pos = block_iter.next().ok_or_else(|| "Sponge: Block data has more entries than expected from boundary".to_string())?;
-                    if *state_index == air_state_index {
-                        continue;
-                    }
-                    block_positions[pos_index] = pos;
-                    state_indices[pos_index] = *state_index as isize;
-                    pos_index += 1;
-
-                    if pos_index >= 4096 {
-                        blocks.set_unknown_blocks(
-                            &block_positions, &state_indices
-                        ).map_err(|e| format!("Sponge: Failed to copy block batch at pos {:?}: {}", pos, e))?;
-                        pos_index = 0;
-                    }
-                }
-                for i in 0..pos_index {
-                    blocks.set_unknown_block(
-                        &block_positions[i], state_indices[i]
-                    ).map_err(|e| format!("Sponge: Failed to copy final block batch at pos {:?}: {}", block_positions[i], e))?;
-                }
-                Ok(())
-            },
+            Value::ByteArray(byte_array) => self.process_block_data(byte_array.deref()),
             _ => {
                 Err("Sponge: 'BlockData' tag is not a ByteArray".into())
             }
         }
     }
 
-    fn read_var_int_array(&mut self, data: &[u8]) -> Result<Vec<i32>, String> {
-        let mut integers = Vec::new();
-        let mut index = 0;
-        while index < data.len() {
-            let mut value = 0;
-            let mut shift = 0;
-            loop {
-                if index >= data.len() {
-                    return Err("Sponge: VarInt array ended unexpectedly".into());
+    fn process_biomes(&mut self, schematic: &HashMap<String, Value>) -> Result<(), String> {
+        // v3 nests a 3D palette and data in 'Biomes', v2 keeps a 2D pair at the schematic level
+        let (palette_tag, data_tag) = match schematic.get("Biomes") {
+            Some(Value::Compound(biomes)) => (biomes.get("Palette"), biomes.get("Data")),
+            Some(_) => return Err("Sponge: 'Biomes' tag is not a Compound".into()),
+            None => (schematic.get("BiomePalette"), schematic.get("BiomeData")),
+        };
+        let (palette_tag, data_tag) = match (palette_tag, data_tag) {
+            (None, None) => return Ok(()),
+            (Some(Value::Compound(palette)), Some(Value::ByteArray(data))) => (palette, data),
+            _ => return Err("Sponge: Biomes need a 'Palette' compound and a 'Data' byte array".into()),
+        };
+        let mut palette = Vec::with_capacity(palette_tag.len());
+        for (name, value) in palette_tag {
+            match value {
+                Value::Int(v) => palette.push((name.clone(), *v)),
+                _ => return Err("Sponge: Biome palette entry value is not an Int".into()),
+            }
+        }
+        self.set_biomes(palette, data_tag.deref())
+    }
+
+    /// Accepts one index per position (v3) or per x/z column (v2).
+    fn set_biomes(&mut self, palette: Vec<(String, i32)>, data: &[i8]) -> Result<(), String> {
+        let boundary = self.boundary.ok_or("Sponge: Boundary not set before processing biomes")?;
+        let palette: HashMap<i32, Rc<str>> = palette.into_iter()
+            .map(|(name, index)| (index, Rc::from(name)))
+            .collect();
+        let indices = read_var_int_array(data)?;
+        let columns = boundary.d_x as usize * boundary.d_z as usize;
+        if indices.len() != boundary.volume() && indices.len() != columns {
+            return Err(format!(
+                "Sponge: Biome data has {} entries, but a {}x{}x{} boundary needs {} or {} per column",
+                indices.len(), boundary.d_x, boundary.d_y, boundary.d_z, boundary.volume(), columns
+            ));
+        }
+        let biomes = indices.iter()
+            .map(|index| palette.get(index).cloned()
+                .ok_or_else(|| format!("Sponge: Biome index {} is not in the palette", index)))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.biomes = Some(biomes);
+        Ok(())
+    }
+
+    /// The biome of a position relative to the schematic origin, `None` without biome data.
+    /// Positions the biome container does not cover get `DEFAULT_BIOME`.
+    fn biome_at(&self, pos: &BlockPosition) -> Option<Rc<str>> {
+        let biomes = self.biomes.as_ref()?;
+        let boundary = self.boundary?;
+        let index = if biomes.len() == boundary.volume() {
+            let local_boundary = Boundary::new_from_size(boundary.d_x, boundary.d_y, boundary.d_z);
+            AxisOrder::YZX.index(pos, &local_boundary) as usize
+        } else {
+            (pos.z() * boundary.d_x + pos.x()) as usize
+        };
+        Some(biomes.get(index).unwrap_or(&self.default_biome).clone())
+    }
+
+    fn process_block_data(&mut self, data: &[i8]) -> Result<(), String> {
+        let boundary = match self.boundary {
+            Some(boundary) => boundary,
+            None => return Err("Sponge: Boundary not set before processing blocks".into()),
+        };
+        if self.blocks.is_none() {
+            return Err("Sponge: Blocks store not initialized before processing blocks".into());
+        }
+        let local_boundary = Boundary::new_from_size(boundary.d_x, boundary.d_y, boundary.d_z);
+        let mut block_iter = local_boundary.iter(AxisOrder::YZX);
+        let blocks = self.blocks.as_mut().unwrap();
+        let air_state_index: i32 = blocks.state_to_temp_id(
+            &BlockState::air_rc()
+        ).map(|t| t as i32).unwrap_or(-1);
+
+        let mut block_positions: [BlockPosition; 4096] = [BlockPosition::new(0, 0, 0); 4096];
+        let mut state_indices: [isize; 4096] = [0; 4096];
+        let mut pos_index = 0;
+        let palette_len = blocks.palette_len();
+        let mut entries = 0;
+
+        // decoded straight from the NBT array, so no copy of the block data is ever held
+        for state_index in var_ints(data) {
+            let state_index = state_index?;
+            entries += 1;
+            // surplus entries are only counted, for the length check below
+            let Some(pos) = block_iter.next() else {
+                continue;
+            };
+            if state_index < 0 {
+                return Err(format!("Sponge: Invalid block state index {} in block data", state_index));
+            }
+            // palettes may skip ids, so only ids the palette declares are valid
+            if !blocks.has_palette_id(state_index as isize) {
+                return Err(format!(
+                    "Sponge: Block state index {} is not in the palette of {} entries",
+                    state_index, palette_len
+                ));
+            }
+            if state_index == air_state_index {
+                continue;
+            }
+            block_positions[pos_index] = pos;
+            state_indices[pos_index] = state_index as isize;
+            pos_index += 1;
+
+            if pos_index >= 4096 {
+                blocks.set_unknown_blocks(
+                    &block_positions, &state_indices
+                ).map_err(|e| format!("Sponge: Failed to copy block batch at pos {:?}: {}", pos, e))?;
+                pos_index = 0;
+            }
+        }
+        if entries != boundary.volume() {
+            return Err(format!(
+                "Sponge: Block data has {} entries, but a {}x{}x{} boundary needs {}",
+                entries, boundary.d_x, boundary.d_y, boundary.d_z, boundary.volume()
+            ));
+        }
+        for i in 0..pos_index {
+            blocks.set_unknown_block(
+                &block_positions[i], state_indices[i]
+            ).map_err(|e| format!("Sponge: Failed to copy final block batch at pos {:?}: {}", block_positions[i], e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a pre-flattening palette key, either `id` or `id:data`.
+fn legacy_id(name: &str) -> Option<(usize, u8)> {
+    match name.split_once(':') {
+        Some((id, data)) => Some((id.parse().ok()?, data.parse().ok()?)),
+        None => Some((name.parse().ok()?, 0)),
+    }
+}
+
+/// Resolves a legacy id the same way the MCEdit reader does: the id table first, then the
+/// property conversion for blocks whose data value encodes their state.
+fn legacy_state(id: usize, data: u8) -> Result<BlockState, String> {
+    match get_legacy_type(id, data) {
+        Some(name) => BlockState::from_string(name),
+        None => convert_legacy_data_to_modern_properties(id, data)
+            .ok_or_else(|| format!("Sponge: Unknown legacy block id {}:{}", id, data)),
+    }
+}
+
+/// The tags of a Sponge schematic the streaming reader needs, collected in a single pass.
+/// NBT does not fix the order of compound entries, so block data is only decoded once all are known.
+#[derive(Default)]
+struct StreamedHeader {
+    width: Option<i16>,
+    height: Option<i16>,
+    length: Option<i16>,
+    offset: Option<Vec<i32>>,
+    palette: Option<Vec<(String, i32)>>,
+    data_version: Option<i32>,
+    data: Option<Vec<i8>>,
+    biome_palette: Option<Vec<(String, i32)>>,
+    biome_data: Option<Vec<i8>>,
+}
+
+impl StreamedHeader {
+    fn parse<R: Read>(parser: &mut Parser<R>) -> Result<Self, String> {
+        let mut header = StreamedHeader::default();
+        // names of the compounds we descended into: the root, v3 'Schematic', its 'Blocks' and 'Biomes'
+        let mut path: Vec<String> = Vec::new();
+        loop {
+            let value = match parser.next() {
+                Ok(value) => value,
+                Err(e) if e.is_eof() && path.is_empty() => break,
+                Err(e) => return Err(e.to_string()),
+            };
+            let in_blocks = path.last().is_some_and(|name| name == "Blocks");
+            let in_biomes = path.last().is_some_and(|name| name == "Biomes");
+            match value {
+                StreamValue::Compound(name) => {
+                    let name = name.unwrap_or_default();
+                    if path.is_empty() || name == "Schematic" || ((name == "Blocks" || name == "Biomes") && !in_blocks && !in_biomes) {
+                        path.push(name);
+                    } else if (name == "Palette" && in_biomes) || name == "BiomePalette" {
+                        header.biome_palette = Some(Self::parse_palette(parser)?);
+                    } else if name == "Palette" {
+                        header.palette = Some(Self::parse_palette(parser)?);
+                    } else {
+                        skip_to_container_end(parser)?;
+                    }
+                }
+                StreamValue::CompoundEnd => {
+                    path.pop();
+                    if path.is_empty() {
+                        break;
+                    }
                 }
-                let byte = data[index];
-                index += 1;
-                value |= ((byte & 0x7F) as i32) << shift;
-                if (byte & 0x80) == 0 {
-                    break;
+                StreamValue::List(..) => skip_to_container_end(parser)?,
+                StreamValue::Short(Some(name), v) => match name.as_str() {
+                    "Width" => header.width = Some(v),
+                    "Height" => header.height = Some(v),
+                    "Length" => header.length = Some(v),
+                    _ => {}
+                },
+                StreamValue::IntArray(Some(name), v) if name == "Offset" => header.offset = Some(v),
+                StreamValue::Int(Some(name), v) if name == "DataVersion" => header.data_version = Some(v),
+                StreamValue::ByteArray(Some(name), v) if name == "BlockData" || (in_blocks && name == "Data") => {
+                    header.data = Some(v);
                 }
-                shift += 7;
-                if shift > 35 {
-                    return Err("Sponge: VarInt is too big".into());
+                StreamValue::ByteArray(Some(name), v) if name == "BiomeData" || (in_biomes && name == "Data") => {
+                    header.biome_data = Some(v);
                 }
+                _ => {}
+            }
+        }
+        Ok(header)
+    }
+
+    fn parse_palette<R: Read>(parser: &mut Parser<R>) -> Result<Vec<(String, i32)>, String> {
+        let mut entries = Vec::new();
+        loop {
+            match parser.next().map_err(|e| e.to_string())? {
+                StreamValue::Int(Some(name), v) => entries.push((name, v)),
+                StreamValue::CompoundEnd => return Ok(entries),
+                _ => return Err("Sponge: Palette entry value is not an Int".into()),
             }
-            integers.push(value);
         }
-        Ok(integers)
     }
 }
 
+/// Reads the NBT byte array `data` as raw bytes, without copying it.
+struct SignedBytes<'a>(&'a [i8]);
+
+impl Read for SignedBytes<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.0.len());
+        for (dst, src) in buf.iter_mut().zip(&self.0[..n]) {
+            *dst = *src as u8;
+        }
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+/// Lazily decodes a byte array of consecutive unsigned LEB128 varints, as used by Sponge 'BlockData'.
+fn var_ints(data: &[i8]) -> impl Iterator<Item = Result<i32, String>> + '_ {
+    let mut bytes = SignedBytes(data);
+    std::iter::from_fn(move || {
+        if bytes.0.is_empty() {
+            return None;
+        }
+        Some(read_var_int(&mut bytes).map_err(|e| match e {
+            SchematicError::Io(_) => "Sponge: VarInt array ended unexpectedly".to_string(),
+            e => format!("Sponge: {}", e),
+        }))
+    })
+}
+
+/// Decodes a whole varint byte array at once, for data that is small or needed as a whole.
+pub(crate) fn read_var_int_array(data: &[i8]) -> Result<Vec<i32>, String> {
+    var_ints(data).collect()
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::common::{Block, BlockPosition, BlockState};
+    use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
+    use crate::stream::any_reader::AnySchematicInputStream;
     use crate::stream::sponge_reader::SpongeSchematicInputStream;
-    use crate::stream::stream::SchematicInputStream;
+    use crate::stream::sponge_writer::SpongeSchematicOutputStream;
+    use crate::stream::stream::{SchematicInputStream, SchematicOutputStream};
+    use fastnbt::{ByteArray, IntArray, Value};
     use flate2::read::GzDecoder;
+    use std::collections::HashMap;
     use std::rc::Rc;
 
     fn create_test_schematic() -> Vec<Block> {
@@ -273,6 +627,7 @@ mod tests {
                         blocks.push(Block {
                             position: BlockPosition::new(x, y, z),
                             state: Rc::new(block_state),
+                            biome: None,
                         });
                     }
                 }
@@ -315,4 +670,305 @@ mod tests {
         assert!(!read_blocks.is_empty(), "Expected to read some blocks from the schematic");
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sponge_read_next_matches_read_to_end() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let mut full_reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        let all_blocks = full_reader.read_to_end_into_vec().unwrap();
+        assert_eq!(all_blocks.len(), create_test_schematic().len());
+
+        let mut chunked_reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        let mut chunked_blocks = Vec::new();
+        while let Some(chunk) = chunked_reader.read_next(10).unwrap() {
+            assert!(chunk.len() <= 10);
+            chunked_blocks.extend(chunk);
+        }
+        assert_eq!(chunked_blocks, all_blocks);
+        assert!(chunked_reader.read_next(10).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sponge_include_air() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let mut sponge_reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        sponge_reader.set_include_air(true);
+        let volume = sponge_reader.boundary().unwrap().unwrap().volume();
+        let read_blocks = sponge_reader.read_to_end_into_vec().unwrap();
+        assert_eq!(read_blocks.len(), volume);
+        let solid = read_blocks.iter().filter(|b| !b.state.is_air()).count();
+        assert_eq!(solid, create_test_schematic().len());
+    }
+
+    #[test]
+    fn test_sponge_read_order() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let mut xyz_reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        let xyz_blocks = xyz_reader.read_to_end_into_vec().unwrap();
+        let mut yzx_reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        assert_eq!(yzx_reader.native_axis_order(), AxisOrder::YZX);
+        yzx_reader.set_read_order(AxisOrder::YZX);
+        let yzx_blocks = yzx_reader.read_to_end_into_vec().unwrap();
+
+        assert_ne!(xyz_blocks, yzx_blocks);
+        let sorted = |blocks: &[Block]| {
+            let mut sorted = blocks.to_vec();
+            sorted.sort_by_key(|b| b.position.to_array());
+            sorted
+        };
+        assert_eq!(sorted(&xyz_blocks), sorted(&yzx_blocks));
+        assert!(yzx_blocks.windows(2).all(|w| w[0].position.y() <= w[1].position.y()));
+    }
+
+    #[test]
+    fn test_sponge_estimated_total() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let mut reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        let boundary = reader.boundary().unwrap().unwrap();
+        assert_eq!(reader.estimated_total().unwrap(), Some(boundary.volume()));
+        let blocks = reader.read_to_end_into_vec().unwrap();
+        assert!(blocks.len() <= boundary.volume());
+
+        let mut any = AnySchematicInputStream::new_from_known(GzDecoder::new(TREE_SCHEMATIC));
+        assert_eq!(any.estimated_total().unwrap(), None);
+        any.read_next(16).unwrap();
+        assert_eq!(any.estimated_total().unwrap(), Some(boundary.volume()));
+    }
+
+    #[test]
+    fn test_sponge_seek_to_middle() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let mut all_cells_reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        all_cells_reader.set_include_air(true);
+        let all_cells = all_cells_reader.read_to_end_into_vec().unwrap();
+        let middle = all_cells.len() / 2;
+
+        let mut reader = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        reader.set_include_air(true);
+        reader.seek_to(middle).unwrap();
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), all_cells[middle..]);
+
+        reader.seek_to(0).unwrap();
+        assert_eq!(reader.read_next(4).unwrap().unwrap(), all_cells[..4]);
+        assert!(reader.seek_to(all_cells.len() + 1).is_err());
+    }
+
+    #[test]
+    fn test_streaming_matches_tree_reader() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let expected = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC))
+            .read_to_end_into_vec()
+            .unwrap();
+        let mut streaming = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        streaming.set_streaming(true);
+        let mut reference = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC));
+        assert_eq!(streaming.boundary().unwrap(), reference.boundary().unwrap());
+        assert_eq!(streaming.read_to_end_into_vec().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_streaming_large_schematic() {
+        let boundary = Boundary::new(-10, 0, 5, 96, 64, 96);
+        let states: Vec<Rc<BlockState>> = ["minecraft:stone", "minecraft:dirt", "minecraft:oak_log[axis=y]"].iter()
+            .map(|name| Rc::new(BlockState::from_str(name).unwrap()))
+            .collect();
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .filter(|pos| (pos.x() + pos.y() + pos.z()) % 5 != 0)
+            .map(|pos| Block::new(states[(pos.x() * 7 + pos.y() * 3 + pos.z()).rem_euclid(3) as usize].clone(), pos))
+            .collect();
+        let mut data = Vec::new();
+        let mut writer = SpongeSchematicOutputStream::new(&mut data, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut reader = SpongeSchematicInputStream::new(data.as_slice());
+        reader.set_streaming(true);
+        assert_eq!(reader.boundary().unwrap(), Some(boundary));
+        let mut count = 0;
+        let mut expected = blocks.iter();
+        while let Some(chunk) = reader.read_next(4096).unwrap() {
+            for block in chunk {
+                assert_eq!(Some(&block), expected.next());
+                count += 1;
+            }
+        }
+        assert_eq!(count, blocks.len());
+    }
+
+    /// A Sponge v2 root compound of `(width, height, length)` with the given palette and block data.
+    fn sponge_v2_root(size: (i16, i16, i16), palette: &[(&str, i32)], data: Vec<i8>) -> HashMap<String, Value> {
+        let palette = palette.iter()
+            .map(|&(state, index)| (state.to_string(), Value::Int(index)))
+            .collect();
+        let mut root = HashMap::new();
+        root.insert("Version".to_string(), Value::Int(2));
+        root.insert("Width".to_string(), Value::Short(size.0));
+        root.insert("Height".to_string(), Value::Short(size.1));
+        root.insert("Length".to_string(), Value::Short(size.2));
+        root.insert("Palette".to_string(), Value::Compound(palette));
+        root.insert("BlockData".to_string(), Value::ByteArray(ByteArray::new(data)));
+        root
+    }
+
+    #[test]
+    fn test_sponge_palette_index_out_of_range() {
+        let root = sponge_v2_root((2, 1, 1), &[("minecraft:air", 0), ("minecraft:stone", 1)], vec![1, 5]);
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+        let mut reader = SpongeSchematicInputStream::new(bytes.as_slice());
+        let error = reader.read_to_end_into_vec().unwrap_err().to_string();
+        assert!(error.contains("Block state index 5 is not in the palette of 2 entries"), "Unexpected error: {}", error);
+    }
+
+    #[test]
+    fn test_sponge_negative_size_is_rejected() {
+        let root = sponge_v2_root((2, -1, 1), &[("minecraft:air", 0)], vec![]);
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+        for streaming in [false, true] {
+            let mut reader = SpongeSchematicInputStream::new(bytes.as_slice());
+            reader.set_streaming(streaming);
+            let error = reader.boundary().unwrap_err().to_string();
+            assert!(error.contains("must not be negative"), "Unexpected error: {}", error);
+        }
+    }
+
+    #[test]
+    fn test_sponge_gapped_palette() {
+        let root = sponge_v2_root((2, 1, 1), &[("minecraft:air", 0), ("minecraft:stone", 5)], vec![0, 5]);
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+        for streaming in [false, true] {
+            let mut reader = SpongeSchematicInputStream::new(bytes.as_slice());
+            reader.set_streaming(streaming);
+            let blocks = reader.read_to_end_into_vec().unwrap();
+            assert_eq!(blocks, vec![Block::from_state_str("minecraft:stone", BlockPosition::new(1, 0, 0)).unwrap()]);
+        }
+    }
+
+    #[test]
+    fn test_sponge_legacy_palette() {
+        let mut root = sponge_v2_root((2, 1, 1), &[("0", 0), ("35:14", 1), ("1", 2)], vec![1, 2]);
+        root.insert("DataVersion".to_string(), Value::Int(1343));
+        let bytes = fastnbt::to_bytes(&Value::Compound(root.clone())).unwrap();
+
+        for streaming in [false, true] {
+            let mut reader = SpongeSchematicInputStream::new(bytes.as_slice());
+            reader.set_streaming(streaming);
+            let states: Vec<String> = reader.read_to_end_into_vec().unwrap()
+                .iter()
+                .map(|block| block.state.to_string())
+                .collect();
+            assert_eq!(states, vec!["minecraft:red_wool", "minecraft:stone"]);
+        }
+
+        // past the flattening, palette keys are taken as state strings and never converted
+        root.insert("DataVersion".to_string(), Value::Int(3465));
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+        let blocks = SpongeSchematicInputStream::new(bytes.as_slice()).read_to_end_into_vec().unwrap();
+        assert_eq!(blocks[0].state.to_string(), "35:14");
+    }
+
+    #[test]
+    fn test_sponge_block_data_length_mismatch() {
+        for (data, entries) in [(vec![1], 1), (vec![1, 0, 1], 3)] {
+            let root = sponge_v2_root((2, 1, 1), &[("minecraft:air", 0), ("minecraft:stone", 1)], data);
+            let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+            let mut reader = SpongeSchematicInputStream::new(bytes.as_slice());
+            let error = reader.read_to_end_into_vec().unwrap_err().to_string();
+            let expected = format!("Block data has {} entries, but a 2x1x1 boundary needs 2", entries);
+            assert!(error.contains(&expected), "Unexpected error: {}", error);
+        }
+    }
+
+    #[test]
+    fn test_sponge_offset() {
+        let mut root = sponge_v2_root((2, 1, 1), &[("minecraft:air", 0), ("minecraft:stone", 1)], vec![0, 1]);
+        root.insert("Offset".to_string(), Value::IntArray(IntArray::new(vec![10, -5, 3])));
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+        let mut reader = SpongeSchematicInputStream::new(bytes.as_slice());
+        let boundary = reader.boundary().unwrap().unwrap();
+        assert_eq!((boundary.min_x, boundary.min_y, boundary.min_z), (10, -5, 3));
+        let blocks = reader.read_to_end_into_vec().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].position, BlockPosition::new(11, -5, 3));
+    }
+
+    #[test]
+    fn test_sponge_v3_biomes() {
+        let schematic_with_biome_data = |data: Vec<i8>| {
+            let mut block_palette = HashMap::new();
+            block_palette.insert("minecraft:stone".to_string(), Value::Int(0));
+            let mut blocks_compound = HashMap::new();
+            blocks_compound.insert("Palette".to_string(), Value::Compound(block_palette));
+            blocks_compound.insert("Data".to_string(), Value::ByteArray(ByteArray::new(vec![0; 4])));
+            let mut biome_palette = HashMap::new();
+            biome_palette.insert("minecraft:desert".to_string(), Value::Int(0));
+            biome_palette.insert("minecraft:swamp".to_string(), Value::Int(1));
+            let mut biomes_compound = HashMap::new();
+            biomes_compound.insert("Palette".to_string(), Value::Compound(biome_palette));
+            biomes_compound.insert("Data".to_string(), Value::ByteArray(ByteArray::new(data)));
+            let mut schematic = HashMap::new();
+            schematic.insert("Version".to_string(), Value::Int(3));
+            schematic.insert("Width".to_string(), Value::Short(2));
+            schematic.insert("Height".to_string(), Value::Short(2));
+            schematic.insert("Length".to_string(), Value::Short(1));
+            schematic.insert("Blocks".to_string(), Value::Compound(blocks_compound));
+            schematic.insert("Biomes".to_string(), Value::Compound(biomes_compound));
+            let mut root = HashMap::new();
+            root.insert("Schematic".to_string(), Value::Compound(schematic));
+            fastnbt::to_bytes(&Value::Compound(root)).unwrap()
+        };
+        let bytes = schematic_with_biome_data(vec![0, 1, 1, 0]);
+        let unknown_index = schematic_with_biome_data(vec![0, 1, 0, 2]);
+
+        for streaming in [false, true] {
+            let mut reader = SpongeSchematicInputStream::new(bytes.as_slice());
+            reader.set_streaming(streaming);
+            reader.set_read_order(AxisOrder::YZX);
+            let blocks = reader.read_to_end_into_vec().unwrap();
+            let biomes: Vec<Option<&str>> = blocks.iter().map(|block| block.biome.as_deref()).collect();
+            assert_eq!(biomes, vec![
+                Some("minecraft:desert"), Some("minecraft:swamp"), Some("minecraft:swamp"), Some("minecraft:desert"),
+            ]);
+            assert_eq!(blocks[1].position, BlockPosition::new(1, 0, 0));
+
+            let mut reader = SpongeSchematicInputStream::new(unknown_index.as_slice());
+            reader.set_streaming(streaming);
+            let error = reader.read_to_end_into_vec().unwrap_err().to_string();
+            assert!(error.contains("Biome index 2 is not in the palette"), "Unexpected error: {}", error);
+        }
+    }
+
+    #[test]
+    fn test_sponge_without_biomes_has_no_biome() {
+        const TREE_SCHEMATIC: &[u8] = include_bytes!("test_schematics/tree.sponge");
+        let blocks = SpongeSchematicInputStream::new(GzDecoder::new(TREE_SCHEMATIC)).read_to_end_into_vec().unwrap();
+        assert!(!blocks.is_empty());
+        assert!(blocks.iter().all(|block| block.biome.is_none()));
+    }
+
+    #[test]
+    fn test_sponge_v2_biomes_are_per_column() {
+        let mut biome_palette = HashMap::new();
+        biome_palette.insert("minecraft:forest".to_string(), Value::Int(0));
+        biome_palette.insert("minecraft:taiga".to_string(), Value::Int(1));
+        let mut root = sponge_v2_root((2, 3, 1), &[("minecraft:stone", 0)], vec![0; 6]);
+        root.insert("BiomePalette".to_string(), Value::Compound(biome_palette));
+        root.insert("BiomeData".to_string(), Value::ByteArray(ByteArray::new(vec![1, 0])));
+        let bytes = fastnbt::to_bytes(&Value::Compound(root)).unwrap();
+
+        for streaming in [false, true] {
+            let mut reader = SpongeSchematicInputStream::new(bytes.as_slice());
+            reader.set_streaming(streaming);
+            let blocks = reader.read_to_end_into_vec().unwrap();
+            assert_eq!(blocks.len(), 6);
+            for block in blocks {
+                let expected = if block.position.x() == 0 { "minecraft:taiga" } else { "minecraft:forest" };
+                assert_eq!(block.biome.as_deref(), Some(expected));
+            }
+        }
+    }
+}
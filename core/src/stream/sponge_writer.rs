@@ -1,56 +1,89 @@
-use crate::common::{AxisOrder, Block, BlockState, Boundary, Region};
+use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
 use crate::store::blockstore::{BlockStore, PagedBlockStore};
+use crate::stream::error::SchematicError;
+use crate::stream::sponge_reader::DEFAULT_BIOME;
 use crate::stream::stream::SchematicOutputStream;
+use crate::stream::varint;
 use fastnbt::{ByteArray, IntArray, Value};
 use std::collections::HashMap;
 use std::io::Write;
+use std::rc::Rc;
+
+/// Minecraft data version written by default (1.19.2).
+pub const DEFAULT_DATA_VERSION: i32 = 3129;
 
 pub struct SpongeSchematicOutputStream<W: Write> {
     writer: W,
     block_store: Box<dyn BlockStore>,
     boundary: Option<Boundary>,
+    data_version: i32,
+    biomes: HashMap<BlockPosition, Rc<str>>,
 }
 
 impl<W: Write> SpongeSchematicOutputStream<W> {
     pub fn new(writer: W, boundary: Boundary) -> Self {
         SpongeSchematicOutputStream {
             writer,
-            block_store: Box::new(PagedBlockStore::new_for_fixed_boundary(boundary)),
+            // complete() walks the store in Sponge's YZX order
+            block_store: Box::new(PagedBlockStore::builder().boundary(boundary).fixed(true).axis_order(AxisOrder::YZX).build()),
             boundary: Some(boundary),
+            data_version: DEFAULT_DATA_VERSION,
+            biomes: HashMap::new(),
         }
     }
 
-    fn encode_var_int(mut value: i32) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        loop {
-            let mut temp = (value & 0x7F) as u8;
-            value >>= 7;
-            if value != 0 {
-                temp |= 0x80;
-            }
-            bytes.push(temp);
-            if value == 0 {
-                break;
-            }
+    /// Sets the Minecraft data version recorded in the file, defaults to `DEFAULT_DATA_VERSION`.
+    /// Importers such as Litematica map states through it, so it should match the source version.
+    pub fn set_data_version(&mut self, data_version: i32) {
+        self.data_version = data_version;
+    }
+
+    /// Lays the biomes out like the block data, filling positions without one with `DEFAULT_BIOME`.
+    fn biomes_compound(&self, boundary: Boundary) -> Value {
+        let default_biome: Rc<str> = Rc::from(DEFAULT_BIOME);
+        let mut palette: HashMap<Rc<str>, i32> = HashMap::new();
+        let mut data = Vec::new();
+        for pos in boundary.iter(AxisOrder::YZX) {
+            let biome = self.biomes.get(&pos).unwrap_or(&default_biome);
+            let palette_size = palette.len() as i32;
+            let index = *palette.entry(biome.clone()).or_insert(palette_size);
+            data.extend(Self::encode_var_int(index));
         }
+        let palette_nbt = palette.into_iter()
+            .map(|(biome, index)| (biome.to_string(), Value::Int(index)))
+            .collect();
+        let mut biomes_compound = HashMap::new();
+        biomes_compound.insert("Palette".to_string(), Value::Compound(palette_nbt));
+        biomes_compound.insert("Data".to_string(), Value::ByteArray(ByteArray::new(data.into_iter().map(|b| b as i8).collect())));
+        Value::Compound(biomes_compound)
+    }
+
+    fn encode_var_int(value: i32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        varint::write_var_int(&mut bytes, value).expect("Writing to a Vec cannot fail");
         bytes
     }
 }
 
 impl<W: Write> SchematicOutputStream for SpongeSchematicOutputStream<W> {
-    fn write(&mut self, blocks: &[Block]) -> Result<usize, String> {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
         self.block_store.insert(blocks, 0, blocks.len())?;
+        for block in blocks {
+            if let Some(biome) = &block.biome {
+                self.biomes.insert(block.position, biome.clone());
+            }
+        }
         Ok(blocks.len())
     }
 
-    fn complete(&mut self) -> Result<(), String> {
+    fn complete(&mut self) -> Result<(), SchematicError> {
         let boundary = self.boundary.ok_or("Sponge: Boundary must be set before closing")?;
         let mut palette = HashMap::new();
         palette.insert(BlockState::air_rc(), 0);
         let mut block_data_bytes = Vec::new();
         for pos in boundary.iter(AxisOrder::YZX) {
             if !self.block_store.contains(&pos) {
-                return Err(format!("Sponge: BlockStore with boundary {:?} is missing position {:?}", boundary, pos));
+                return Err(format!("Sponge: BlockStore with boundary {:?} is missing position {:?}", boundary, pos).into());
             }
             let state_index = match self.block_store.block_at(&pos)? {
                 None => 0,
@@ -71,18 +104,22 @@ impl<W: Write> SchematicOutputStream for SpongeSchematicOutputStream<W> {
 
         let mut schematic_compound = HashMap::new();
         schematic_compound.insert("Version".to_string(), Value::Int(3));
-        schematic_compound.insert("DataVersion".to_string(), Value::Int(3129));
+        schematic_compound.insert("DataVersion".to_string(), Value::Int(self.data_version));
         schematic_compound.insert("Width".to_string(), Value::Short(boundary.d_x as i16));
         schematic_compound.insert("Height".to_string(), Value::Short(boundary.d_y as i16));
         schematic_compound.insert("Length".to_string(), Value::Short(boundary.d_z as i16));
-        schematic_compound.insert("Offset".to_string(), Value::IntArray(IntArray::new(vec![0, 0, 0])));
+        schematic_compound.insert("Offset".to_string(), Value::IntArray(IntArray::new(vec![boundary.min_x, boundary.min_y, boundary.min_z])));
 
         let mut blocks_compound = HashMap::new();
         blocks_compound.insert("Palette".to_string(), Value::Compound(palette_nbt));
         let byte_array: Vec<i8> = block_data_bytes.into_iter().map(|b| b as i8).collect();
         blocks_compound.insert("Data".to_string(), Value::ByteArray(ByteArray::new(byte_array)));
+        // fastnbt encodes an empty list as TAG_End with length 0, which is the spec's form for a typeless empty list.
         blocks_compound.insert("BlockEntities".to_string(), Value::List(Vec::new()));
         schematic_compound.insert("Blocks".to_string(), Value::Compound(blocks_compound));
+        if !self.biomes.is_empty() {
+            schematic_compound.insert("Biomes".to_string(), self.biomes_compound(boundary));
+        }
 
         schematic_compound.insert("Metadata".to_string(), Value::Compound({
             let mut meta = HashMap::new();
@@ -95,6 +132,117 @@ impl<W: Write> SchematicOutputStream for SpongeSchematicOutputStream<W> {
         let nbt_data = Value::Compound(root);
         let encoded = fastnbt::to_bytes(&nbt_data).map_err(|e| format!("Sponge: NBT encoding error: {}", e))?;
         self.writer.write_all(&encoded).map_err(|e| e.to_string())?;
-        Ok(())
+        self.writer.flush().map_err(SchematicError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Block, BlockPosition, BlockState, Boundary};
+    use crate::stream::sponge_reader::SpongeSchematicInputStream;
+    use crate::stream::sponge_writer::SpongeSchematicOutputStream;
+    use crate::stream::stream::{SchematicInputStream, SchematicOutputStream, SharedBuffer};
+    use fastnbt::stream::{Parser, Value as StreamValue};
+    use fastnbt::{Tag, Value};
+    use std::cell::RefCell;
+    use std::io::BufWriter;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_complete_flushes_buffered_output() {
+        let boundary = Boundary::new_from_size(2, 1, 1);
+        let blocks = vec![Block::from_state_str("minecraft:stone", BlockPosition::new(1, 0, 0)).unwrap()];
+        let bytes = Rc::new(RefCell::new(Vec::new()));
+        let mut writer = SpongeSchematicOutputStream::new(BufWriter::new(SharedBuffer(bytes.clone())), boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        // the writer still holds the BufWriter, so the bytes can only be there if complete flushed them
+        let read = SpongeSchematicInputStream::new(bytes.borrow().as_slice()).read_to_end_into_vec().unwrap();
+        assert_eq!(read, blocks);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_data_version_round_trip() {
+        let boundary = Boundary::new_from_size(3, 2, 2);
+        let blocks = vec![
+            Block::new(Rc::new(BlockState::from_str("minecraft:stone").unwrap()), BlockPosition::new(0, 0, 0)),
+            Block::new(Rc::new(BlockState::from_str("minecraft:oak_log[axis=z]").unwrap()), BlockPosition::new(2, 1, 1)),
+        ];
+        let mut data = Vec::new();
+        let mut writer = SpongeSchematicOutputStream::new(&mut data, boundary);
+        writer.set_data_version(3465);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let root: Value = fastnbt::from_bytes(&data).unwrap();
+        let schematic = match root {
+            Value::Compound(mut root) => root.remove("Schematic").unwrap(),
+            _ => panic!("Root is not a compound"),
+        };
+        match schematic {
+            Value::Compound(schematic) => {
+                assert_eq!(schematic.get("Version"), Some(&Value::Int(3)));
+                assert_eq!(schematic.get("DataVersion"), Some(&Value::Int(3465)));
+            }
+            _ => panic!("Schematic is not a compound"),
+        }
+
+        let mut reader = SpongeSchematicInputStream::new(data.as_slice());
+        assert_eq!(reader.boundary().unwrap(), Some(boundary));
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), blocks);
+    }
+
+    #[test]
+    fn test_empty_block_entities_list() {
+        let boundary = Boundary::new_from_size(2, 1, 1);
+        let blocks = vec![Block::new(Rc::new(BlockState::from_str("minecraft:stone").unwrap()), BlockPosition::new(1, 0, 0))];
+        let mut data = Vec::new();
+        let mut writer = SpongeSchematicOutputStream::new(&mut data, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut parser = Parser::new(data.as_slice());
+        let mut block_entities = None;
+        let mut depth = 0;
+        loop {
+            match parser.next().unwrap() {
+                StreamValue::List(Some(name), tag, len) if name == "BlockEntities" => block_entities = Some((tag, len)),
+                StreamValue::Compound(_) => depth += 1,
+                StreamValue::CompoundEnd => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(block_entities, Some((Tag::End, 0)));
+
+        let mut reader = SpongeSchematicInputStream::new(data.as_slice());
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), blocks);
+    }
+
+    #[test]
+    fn test_biome_round_trip() {
+        let boundary = Boundary::new_from_size(2, 2, 1);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks = vec![
+            Block::new(stone.clone(), BlockPosition::new(0, 0, 0)).with_biome("minecraft:desert"),
+            Block::new(stone.clone(), BlockPosition::new(1, 1, 0)).with_biome("minecraft:cherry_grove"),
+            Block::new(stone.clone(), BlockPosition::new(0, 1, 0)),
+        ];
+        let mut data = Vec::new();
+        let mut writer = SpongeSchematicOutputStream::new(&mut data, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut reader = SpongeSchematicInputStream::new(data.as_slice());
+        let read_blocks = reader.read_to_end_into_vec().unwrap();
+        let biomes: Vec<Option<&str>> = read_blocks.iter().map(|block| block.biome.as_deref()).collect();
+        assert_eq!(biomes, vec![Some("minecraft:desert"), Some("minecraft:plains"), Some("minecraft:cherry_grove")]);
+        assert_eq!(read_blocks[0], blocks[0]);
+        assert_eq!(read_blocks[2], blocks[1]);
+    }
+}
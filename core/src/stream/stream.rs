@@ -1,14 +1,53 @@
 use crate::common::{AxisOrder, Block, Boundary};
 use crate::store::blockstore::BlockStore;
+use crate::stream::error::SchematicError;
+use crate::stream::format::SchematicFormat;
+
+/// Default upper bound for the declared volume of a schematic, in blocks.
+pub const DEFAULT_MAX_VOLUME: usize = 64 * 1024 * 1024;
+
+/// Rejects declared sizes above `max_volume` before anything gets allocated for them.
+pub fn check_volume(size_x: usize, size_y: usize, size_z: usize, max_volume: usize) -> Result<(), String> {
+    let volume = size_x.checked_mul(size_y).and_then(|v| v.checked_mul(size_z));
+    match volume {
+        Some(volume) if volume <= max_volume => Ok(()),
+        _ => Err(format!(
+            "Declared size {}x{}x{} exceeds the maximum volume of {} blocks",
+            size_x, size_y, size_z, max_volume
+        )),
+    }
+}
+
+/// Validates a `seek_to` target against the boundary volume of `stream` and returns it.
+/// Seeking to the volume itself is allowed and leaves nothing to read.
+pub(crate) fn checked_seek_index<S: SchematicInputStream + ?Sized>(
+    stream: &mut S,
+    format_name: &str,
+    block_index: usize,
+) -> Result<usize, SchematicError> {
+    let volume = stream.boundary()?.map_or(0, |boundary| boundary.volume());
+    if block_index > volume {
+        return Err(SchematicError::OutOfBounds(format!(
+            "{}: Cannot seek to block {} of a schematic with {} blocks",
+            format_name, block_index, volume
+        )));
+    }
+    Ok(block_index)
+}
+
+/// A per-chunk block transformation applied by [`SchematicInputStream::transfer_with`].
+pub type BlockTransform = Box<dyn Fn(Vec<Block>) -> Vec<Block>>;
 
 /// A stream for reading schematic data block by block.
 pub trait SchematicInputStream {
     /// Reads up to `length` blocks into the provided buffer starting from `offset`.
     /// Returns the number of blocks read, or `None` if the end of the stream is reached.
     fn read(& mut self, buffer: &mut Vec<Block>, offset: usize, length: usize)
-            -> Result<Option<usize>, String>;
+            -> Result<Option<usize>, SchematicError>;
     
-    fn read_next(&mut self, limit: usize) -> Result<Option<Vec<Block>>, String> {
+    /// Reads up to `limit` blocks into a freshly allocated vector.
+    /// Returns `None` once the end of the stream is reached.
+    fn read_next(&mut self, limit: usize) -> Result<Option<Vec<Block>>, SchematicError> {
         let mut buffer = Vec::with_capacity(limit);
         if let Some(_read_blocks) = self.read(&mut buffer, 0, limit)? {
             Ok(Some(buffer))
@@ -17,11 +56,48 @@ pub trait SchematicInputStream {
         }
     }
 
-    fn transfer_into(&mut self, mut store: Box<dyn SchematicOutputStream>) -> Result<(), String> {
+    /// Reads up to `length` blocks into the front of an existing slice, clamped to its length.
+    /// Slots past the returned count are left untouched.
+    /// Returns `None` once the end of the stream is reached.
+    fn read_into_slice(&mut self, buf: &mut [Option<Block>], length: usize) -> Result<Option<usize>, SchematicError> {
+        let length = length.min(buf.len());
+        let mut blocks = Vec::with_capacity(length);
+        match self.read(&mut blocks, 0, length)? {
+            Some(_) => {
+                let read_blocks = blocks.len();
+                for (slot, block) in buf.iter_mut().zip(blocks) {
+                    *slot = Some(block);
+                }
+                Ok(Some(read_blocks))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Copies every remaining block into the output stream and completes it.
+    /// The source boundary, if known, is handed to the output first.
+    fn transfer_into(&mut self, store: Box<dyn SchematicOutputStream>) -> Result<(), SchematicError> {
+        self.transfer_with(store, &[])
+    }
+
+    /// Like `transfer_into`, but runs every chunk through `transforms` in order before writing it.
+    /// Transforms may move blocks, so the source boundary is only handed over when there are none.
+    fn transfer_with(
+        &mut self,
+        mut store: Box<dyn SchematicOutputStream>,
+        transforms: &[BlockTransform],
+    ) -> Result<(), SchematicError> {
+        if transforms.is_empty() {
+            if let Some(boundary) = self.boundary()? {
+                store.set_boundary(boundary)?;
+            }
+        }
         loop {
             let mut blocks = Vec::new();
             if let Some(read_blocks) = self.read(&mut blocks, 0, 4096)? {
-                store.write(&blocks[..read_blocks])?;
+                blocks.truncate(read_blocks);
+                let blocks = transforms.iter().fold(blocks, |blocks, transform| transform(blocks));
+                store.write(&blocks)?;
             } else {
                 break;
             }
@@ -32,7 +108,7 @@ pub trait SchematicInputStream {
     
     /// Reads all blocks from the input stream into the given BlockStore.
     /// This method handles buffering internally for efficiency.
-    fn read_to_end(&mut self, store: &mut dyn BlockStore) -> Result<(), String> {
+    fn read_to_end(&mut self, store: &mut dyn BlockStore) -> Result<(), SchematicError> {
         loop {
             let mut blocks = Vec::new();
             if let Some(read_blocks) = self.read(&mut blocks, 0, 4096)? {
@@ -47,21 +123,37 @@ pub trait SchematicInputStream {
     /// Reads all blocks from the input stream into a Vec<Block>.
     /// This method handles buffering internally for efficiency.
     /// Note: This can consume a lot of memory for large schematics, so use with caution.
-    fn read_to_end_into_vec(&mut self) -> Result<Vec<Block>, String> {
+    fn read_to_end_into_vec(&mut self) -> Result<Vec<Block>, SchematicError> {
         let mut blocks = Vec::new();
-        loop {
-            let mut chunk = Vec::new();
-            if let Some(_read_blocks) = self.read(&mut chunk, 0, 4096)? {
-                blocks.extend(chunk);
-            } else {
-                break;
-            }
+        while let Some(chunk) = self.read_next(4096)? {
+            blocks.extend(chunk);
         }
         Ok(blocks)
     }
 
     /// Retrieves the boundary information of the schematic, if available.
-    fn boundary(&mut self) -> Result<Option<Boundary>, String>;
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError>;
+
+    /// Upper bound for the number of cells the stream covers, meant for progress reporting.
+    /// The boundary volume by default, `None` for streams without a declared size.
+    /// Air is skipped when reading, so fewer blocks may actually be returned.
+    /// Errors reading the header are returned rather than reported as an unknown size.
+    fn estimated_total(&mut self) -> Result<Option<usize>, SchematicError> {
+        Ok(self.boundary()?.map(|boundary| boundary.volume()))
+    }
+
+    /// Moves the read cursor to the `block_index`-th cell of the boundary, counted in read order
+    /// and including air, so the next `read` continues from there.
+    /// Formats that hold the whole schematic in memory after the header seek anywhere,
+    /// streamed formats may only support seeking forward.
+    fn seek_to(&mut self, _block_index: usize) -> Result<(), SchematicError> {
+        Err("Seeking is not supported by this stream".into())
+    }
+
+    /// The file format this stream decodes, `None` for wrappers and streams not tied to a format.
+    fn format(&self) -> Option<SchematicFormat> {
+        None
+    }
 }
 
 /// A stream for writing schematic data block by block.
@@ -70,11 +162,18 @@ pub trait SchematicInputStream {
 pub trait SchematicOutputStream {
     /// Writes a slice of blocks to the output stream.
     /// Returns the number of blocks written.
-    fn write(&mut self, blocks: &[Block]) -> Result<usize, String>;
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError>;
+
+    /// Announces the boundary of the data about to be written.
+    /// Formats that store their size use it to keep surrounding air, others ignore it.
+    /// Should be called before the first write.
+    fn set_boundary(&mut self, _boundary: Boundary) -> Result<(), SchematicError> {
+        Ok(())
+    }
 
     /// Writes all blocks from the given BlockStore to the output stream.
     /// This method handles buffering internally for efficiency.
-    fn write_all(&mut self, blocks: &mut dyn BlockStore) -> Result<(), String> {
+    fn write_all(&mut self, blocks: &mut dyn BlockStore) -> Result<(), SchematicError> {
         let iter = blocks.block_iterator(AxisOrder::XYZ);
         let chunk_size = 4096;
         let mut buffer = Vec::with_capacity(chunk_size);
@@ -93,7 +192,78 @@ pub trait SchematicOutputStream {
         Ok(())
     }
 
+    /// Pushes bytes written so far to the underlying writer without finalizing the output.
+    /// Formats that only encode on `complete()` have nothing to push and keep this no-op.
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        Ok(())
+    }
+
     /// Completes the output stream, finalizing any necessary data.
     /// This must be called after all writes are done.
-    fn complete(&mut self) -> Result<(), String>;
+    fn complete(&mut self) -> Result<(), SchematicError>;
+}
+
+
+/// Collects everything written into a shared vector, handy for testing stream wrappers.
+#[cfg(test)]
+impl SchematicOutputStream for std::rc::Rc<std::cell::RefCell<Vec<Block>>> {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        self.borrow_mut().extend_from_slice(blocks);
+        Ok(blocks.len())
+    }
+
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        Ok(())
+    }
+}
+
+/// A writer appending into a shared byte vector, so tests can inspect what a boxed stream wrote.
+#[cfg(test)]
+pub(crate) struct SharedBuffer(pub(crate) std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
+    use crate::stream::stream::{SchematicInputStream, SchematicOutputStream};
+    use crate::stream::vxl_reader::VXLSchematicInputStream;
+    use crate::stream::vxl_writer::VXLSchematicOutputStream;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_read_into_fixed_slice() {
+        let boundary = Boundary::new_from_size(10, 10, 10);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ).map(|pos| Block::new(stone.clone(), pos)).collect();
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, AxisOrder::XYZ, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(data));
+        let mut buffer: Vec<Option<Block>> = vec![None; 256];
+        let mut positions: Vec<BlockPosition> = Vec::new();
+        while let Some(read) = reader.read_into_slice(&mut buffer, 1024).unwrap() {
+            assert!(read <= 256);
+            positions.extend(buffer[..read].iter().map(|b| b.as_ref().unwrap().position));
+        }
+        assert_eq!(buffer.len(), 256);
+        assert_eq!(positions.len(), blocks.len());
+        positions.sort_by_key(|p| p.to_array());
+        let mut expected: Vec<BlockPosition> = blocks.iter().map(|b| b.position).collect();
+        expected.sort_by_key(|p| p.to_array());
+        assert_eq!(positions, expected);
+    }
 }
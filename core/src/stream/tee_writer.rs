@@ -0,0 +1,91 @@
+use crate::common::{Block, Boundary};
+use crate::stream::error::SchematicError;
+use crate::stream::stream::SchematicOutputStream;
+
+/// Forwards every block to two output streams, e.g. to produce two formats in a single pass.
+pub struct TeeOutputStream {
+    first: Box<dyn SchematicOutputStream>,
+    second: Box<dyn SchematicOutputStream>,
+}
+
+impl TeeOutputStream {
+    pub fn new(first: Box<dyn SchematicOutputStream>, second: Box<dyn SchematicOutputStream>) -> Self {
+        TeeOutputStream { first, second }
+    }
+}
+
+impl SchematicOutputStream for TeeOutputStream {
+    /// Returns the smaller of the two written counts.
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        let first = self.first.write(blocks)?;
+        let second = self.second.write(blocks)?;
+        Ok(first.min(second))
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) -> Result<(), SchematicError> {
+        self.first.set_boundary(boundary)?;
+        self.second.set_boundary(boundary)
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+
+    /// Completes both streams even if the first one fails, reporting every error.
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        match (self.first.complete(), self.second.complete()) {
+            (Ok(()), Ok(())) => Ok(()),
+            (Err(e), Ok(())) | (Ok(()), Err(e)) => Err(e),
+            (Err(first), Err(second)) => Err(SchematicError::Parse(format!(
+                "Tee: Both outputs failed to complete: {}; {}",
+                first, second
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary};
+    use crate::stream::sponge_reader::SpongeSchematicInputStream;
+    use crate::stream::sponge_writer::SpongeSchematicOutputStream;
+    use crate::stream::stream::{SchematicInputStream, SchematicOutputStream, SharedBuffer};
+    use crate::stream::tee_writer::TeeOutputStream;
+    use crate::stream::vxl_reader::VXLSchematicInputStream;
+    use crate::stream::vxl_writer::VXLSchematicOutputStream;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_tee_into_vxl_and_sponge() {
+        let boundary = Boundary::new_from_size(4, 2, 3);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let log = Rc::new(BlockState::from_str("minecraft:oak_log[axis=x]").unwrap());
+        let blocks = vec![
+            Block::new(stone.clone(), BlockPosition::new(0, 0, 0)),
+            Block::new(stone.clone(), BlockPosition::new(1, 0, 2)),
+            Block::new(log.clone(), BlockPosition::new(3, 1, 2)),
+        ];
+
+        let vxl = Rc::new(RefCell::new(Vec::new()));
+        let sponge = Rc::new(RefCell::new(Vec::new()));
+        let mut tee = TeeOutputStream::new(
+            Box::new(VXLSchematicOutputStream::new(SharedBuffer(vxl.clone()), AxisOrder::XYZ, boundary)),
+            Box::new(SpongeSchematicOutputStream::new(SharedBuffer(sponge.clone()), boundary)),
+        );
+        assert_eq!(tee.write(&blocks).unwrap(), blocks.len());
+        tee.complete().unwrap();
+
+        let sorted = |mut blocks: Vec<Block>| {
+            blocks.sort_by_key(|b| b.position.to_array());
+            blocks
+        };
+        let vxl_bytes = vxl.borrow().clone();
+        let sponge_bytes = sponge.borrow().clone();
+        let from_vxl = VXLSchematicInputStream::new(vxl_bytes.as_slice()).read_to_end_into_vec().unwrap();
+        let from_sponge = SpongeSchematicInputStream::new(sponge_bytes.as_slice()).read_to_end_into_vec().unwrap();
+        assert_eq!(sorted(from_vxl), sorted(blocks.clone()));
+        assert_eq!(sorted(from_sponge), sorted(blocks));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::stream::error::SchematicError;
+use std::io::{Read, Write};
+
+/// Longest valid encodings: 32 and 64 bits in groups of 7.
+const MAX_VAR_INT_BYTES: usize = 5;
+const MAX_VAR_LONG_BYTES: usize = 10;
+
+/// Reads an LEB128 varint as written by `write_var_int`. Negative values take the full five bytes.
+/// Encodings longer than five bytes, or whose last byte carries bits past the 32nd, are rejected.
+pub fn read_var_int<R: Read + ?Sized>(reader: &mut R) -> Result<i32, SchematicError> {
+    let mut value: u32 = 0;
+    for i in 0..MAX_VAR_INT_BYTES {
+        let byte = read_byte(reader)?;
+        if i == MAX_VAR_INT_BYTES - 1 && byte & 0xF0 != 0 {
+            break;
+        }
+        value |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(SchematicError::Parse("VarInt is too big".into()))
+}
+
+/// Reads an LEB128 varlong, with the same limits as `read_var_int` scaled to ten bytes.
+pub fn read_var_long<R: Read + ?Sized>(reader: &mut R) -> Result<i64, SchematicError> {
+    let mut value: u64 = 0;
+    for i in 0..MAX_VAR_LONG_BYTES {
+        let byte = read_byte(reader)?;
+        if i == MAX_VAR_LONG_BYTES - 1 && byte & 0xFE != 0 {
+            break;
+        }
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i64);
+        }
+    }
+    Err(SchematicError::Parse("VarLong is too big".into()))
+}
+
+/// Writes `value` as an LEB128 varint of its two's complement bits, one to five bytes.
+pub fn write_var_int<W: Write + ?Sized>(writer: &mut W, value: i32) -> std::io::Result<()> {
+    let mut buf = [0u8; MAX_VAR_INT_BYTES];
+    let len = encode(value as u32 as u64, &mut buf);
+    writer.write_all(&buf[..len])
+}
+
+/// Writes `value` as an LEB128 varlong of its two's complement bits, one to ten bytes.
+pub fn write_var_long<W: Write + ?Sized>(writer: &mut W, value: i64) -> std::io::Result<()> {
+    let mut buf = [0u8; MAX_VAR_LONG_BYTES];
+    let len = encode(value as u64, &mut buf);
+    writer.write_all(&buf[..len])
+}
+
+fn encode(mut value: u64, buf: &mut [u8]) -> usize {
+    let mut pos = 0;
+    while value & !0x7F != 0 {
+        buf[pos] = (value & 0x7F) as u8 | 0x80;
+        value >>= 7;
+        pos += 1;
+    }
+    buf[pos] = value as u8;
+    pos + 1
+}
+
+fn read_byte<R: Read + ?Sized>(reader: &mut R) -> Result<u8, SchematicError> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::error::SchematicError;
+    use crate::stream::varint::{read_var_int, read_var_long, write_var_int, write_var_long};
+
+    fn encode_int(value: i32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_var_int(&mut bytes, value).unwrap();
+        bytes
+    }
+
+    fn encode_long(value: i64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_var_long(&mut bytes, value).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_var_int_boundary_values() {
+        for (value, len) in [(0, 1), (127, 1), (128, 2), (i32::MAX, 5), (-1, 5), (i32::MIN, 5)] {
+            let bytes = encode_int(value);
+            assert_eq!(bytes.len(), len, "length of {}", value);
+            assert_eq!(read_var_int(&mut bytes.as_slice()).unwrap(), value);
+        }
+        assert_eq!(encode_int(-1), vec![0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+        for (value, len) in [(0, 1), (i64::MAX, 9), (-1, 10), (i64::MIN, 10), (0x56584C44524D, 7)] {
+            let bytes = encode_long(value);
+            assert_eq!(bytes.len(), len, "length of {}", value);
+            assert_eq!(read_var_long(&mut bytes.as_slice()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_malformed_var_ints() {
+        // six bytes, and five bytes with bits past the 32nd set
+        let too_long = [0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+        assert!(matches!(read_var_int(&mut &too_long[..]), Err(SchematicError::Parse(_))));
+        let overflowing = [0xFF, 0xFF, 0xFF, 0xFF, 0x1F];
+        assert!(matches!(read_var_int(&mut &overflowing[..]), Err(SchematicError::Parse(_))));
+        let mut long_too_long = vec![0x80; 10];
+        long_too_long.push(0x01);
+        assert!(matches!(read_var_long(&mut long_too_long.as_slice()), Err(SchematicError::Parse(_))));
+        let long_overflowing = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x03];
+        assert!(matches!(read_var_long(&mut &long_overflowing[..]), Err(SchematicError::Parse(_))));
+
+        let truncated = [0x80, 0x80];
+        assert!(matches!(read_var_int(&mut &truncated[..]), Err(SchematicError::Io(_))));
+    }
+}
@@ -1,12 +1,19 @@
 use crate::common::{AxisOrder, Block, BlockState, Boundary, Region};
-use crate::stream::stream::SchematicInputStream;
+use crate::stream::error::SchematicError;
+use crate::stream::format::SchematicFormat;
+use crate::stream::stream::{checked_seek_index, SchematicInputStream};
+use crate::stream::varint;
 use std::cmp::min;
 use std::collections::HashMap;
-use std::io::Read;
+use std::io::{ErrorKind, Read};
 use std::rc::Rc;
 
 const MAGIC_NUMBER: i64 = 0x56584C44524D;
 const VERSION: i32 = 1;
+const METADATA_VERSION: i32 = 2;
+/// The longest string readers accept unless configured otherwise, also the metadata limit of writers.
+pub const DEFAULT_MAX_STRING_LENGTH: usize = 64 * 1024;
+const DEFAULT_MAX_PALETTE_ENTRIES: usize = 1 << 20;
 
 pub struct VXLSchematicInputStream<R: Read> {
     reader: R,
@@ -17,16 +24,19 @@ pub struct VXLSchematicInputStream<R: Read> {
     read_blocks : usize,
     current_run_state: Option<Rc<BlockState>>,
     remaining_run_length: i32,
+    metadata: Option<String>,
+    max_string_length: usize,
+    max_palette_entries: usize,
 }
 
 impl<R: Read> SchematicInputStream for VXLSchematicInputStream<R> {
-    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, String> {
+    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, SchematicError> {
         if !self.header_read {
             self.read_header()?;
         }
 
-        let boundary = self.boundary.ok_or_else(|| "VXL: Missing boundary")?;
-        let axis_order = self.axis_order.ok_or_else(|| "VXL: Missing axis order")?;
+        let boundary = self.boundary.ok_or_else(|| SchematicError::Parse("VXL: Missing boundary".into()))?;
+        let axis_order = self.axis_order.ok_or_else(|| SchematicError::Parse("VXL: Missing axis order".into()))?;
 
         let mut blocks_written = 0;
 
@@ -37,6 +47,17 @@ impl<R: Read> SchematicInputStream for VXLSchematicInputStream<R> {
                 }
             }
 
+            if self.current_run_state.as_ref().is_some_and(|state| state.is_air()) {
+                // nothing is emitted for air, so the whole run is skipped without walking its positions
+                let run_length = self.remaining_run_length as usize;
+                if self.read_blocks + run_length > boundary.volume() {
+                    return Err(SchematicError::OutOfBounds(format!("VXL: Air run of {} blocks overruns the boundary after reading {} blocks", run_length, self.read_blocks)));
+                }
+                self.read_blocks += run_length;
+                self.remaining_run_length = 0;
+                continue;
+            }
+
             let attempt_to_process = min(
                 (length - blocks_written) as i32,
                 self.remaining_run_length
@@ -45,14 +66,13 @@ impl<R: Read> SchematicInputStream for VXLSchematicInputStream<R> {
             if let Some(state) = &self.current_run_state {
                 let mut pos_iter = boundary.iter(axis_order).skip(self.read_blocks);
                 for _ in 0..attempt_to_process {
-                    let pos = pos_iter.next().ok_or_else(|| format!("VXL: Ran out of positions in boundary after reading {} blocks", self.read_blocks))?;
-                    if !state.is_air() {
-                        buffer.push(Block {
-                            position: pos,
-                            state: Rc::clone(state),
-                        });
-                        blocks_written += 1;
-                    }
+                    let pos = pos_iter.next().ok_or_else(|| SchematicError::OutOfBounds(format!("VXL: Ran out of positions in boundary after reading {} blocks", self.read_blocks)))?;
+                    buffer.push(Block {
+                        position: pos,
+                        state: Rc::clone(state),
+                        biome: None,
+                    });
+                    blocks_written += 1;
                     self.read_blocks += 1;
                 }
             }
@@ -66,12 +86,37 @@ impl<R: Read> SchematicInputStream for VXLSchematicInputStream<R> {
         }
     }
 
-    fn boundary(&mut self) -> Result<Option<Boundary>, String> {
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
         if !self.header_read {
             self.read_header()?;
         }
         Ok(self.boundary)
     }
+
+    /// VXL is decoded front to back, so only forward seeks are possible. The runs before
+    /// `block_index` are skipped without emitting their blocks.
+    fn seek_to(&mut self, block_index: usize) -> Result<(), SchematicError> {
+        let block_index = checked_seek_index(self, "VXL", block_index)?;
+        if block_index < self.read_blocks {
+            return Err(SchematicError::OutOfBounds(format!(
+                "VXL: Cannot seek back to block {} after reading {} blocks",
+                block_index, self.read_blocks
+            )));
+        }
+        while self.read_blocks < block_index {
+            if self.remaining_run_length <= 0 && !self.parse_next_instruction()? {
+                break;
+            }
+            let skipped = min(self.remaining_run_length as usize, block_index - self.read_blocks);
+            self.read_blocks += skipped;
+            self.remaining_run_length -= skipped as i32;
+        }
+        Ok(())
+    }
+
+    fn format(&self) -> Option<SchematicFormat> {
+        Some(SchematicFormat::Vxl)
+    }
 }
 
 impl<R: Read> VXLSchematicInputStream<R> {
@@ -84,23 +129,60 @@ impl<R: Read> VXLSchematicInputStream<R> {
             read_blocks: 0,
             current_run_state: None,
             remaining_run_length: 0,
+            metadata: None,
+            max_string_length: DEFAULT_MAX_STRING_LENGTH,
+            max_palette_entries: DEFAULT_MAX_PALETTE_ENTRIES,
+        }
+    }
+
+    /// Returns the palette entries discovered so far, in id order.
+    /// The palette is built while reading, so it only holds the states seen up to the current position.
+    pub fn palette_snapshot(&self) -> Vec<Rc<BlockState>> {
+        let mut entries: Vec<(&i32, &Rc<BlockState>)> = self.palette.iter().collect();
+        entries.sort_by_key(|(id, _)| **id);
+        entries.into_iter().map(|(_, state)| Rc::clone(state)).collect()
+    }
+
+    /// Sets the longest string (block state, diff or metadata) the reader accepts, in bytes.
+    pub fn set_max_string_length(&mut self, max_string_length: usize) {
+        self.max_string_length = max_string_length;
+    }
+
+    /// Sets how many palette entries the reader accepts before giving up.
+    pub fn set_max_palette_entries(&mut self, max_palette_entries: usize) {
+        self.max_palette_entries = max_palette_entries;
+    }
+
+    fn check_palette_capacity(&self) -> Result<(), SchematicError> {
+        if self.palette.len() >= self.max_palette_entries {
+            return Err(SchematicError::Parse(format!("VXL: Palette exceeds the maximum of {} entries", self.max_palette_entries)));
         }
+        Ok(())
+    }
+
+    /// Returns the metadata string stored in a version 2 file, which may be empty.
+    /// This is `None` for version 1 files and until the header has been read.
+    pub fn metadata(&self) -> Option<String> {
+        self.metadata.clone()
     }
 
-    pub fn read_header(&mut self) -> Result<(Boundary, AxisOrder), String> {
+    pub fn read_header(&mut self) -> Result<(Boundary, AxisOrder), SchematicError> {
         if self.header_read {
-            return Err("VXL: Header already read".into());
+            return Err(SchematicError::Parse("VXL: Header already read".into()));
         }
         let magic = self.read_var_long()?;
         if magic != MAGIC_NUMBER {
-            return Err(format!("VXL: Invalid magic number. Expected 0x{:X}, got 0x{:X}", MAGIC_NUMBER, magic));
+            return Err(SchematicError::Parse(format!("VXL: Invalid magic number. Expected 0x{:X}, got 0x{:X}", MAGIC_NUMBER, magic)));
         }
         let version = self.read_var_int()?;
-        if version != VERSION {
-            return Err(format!("VXL: Unsupported version. Expected {}, got {}", VERSION, version));
+        if version != VERSION && version != METADATA_VERSION {
+            return Err(SchematicError::UnsupportedVersion(version));
         }
         let boundary = self.read_boundary()?;
         let axis_order = self.read_axis_order()?;
+        if version == METADATA_VERSION {
+            self.metadata = Some(self.read_string()?);
+        }
 
         self.boundary = Some(boundary);
         self.axis_order = Some(axis_order);
@@ -109,97 +191,115 @@ impl<R: Read> VXLSchematicInputStream<R> {
         Ok((boundary, axis_order))
     }
 
-    fn parse_next_instruction(&mut self) -> Result<bool, String> {
+    /// Returns `Ok(false)` only on a clean end of stream between instructions.
+    /// Running out of bytes inside an instruction means the file was cut off and is reported as an error.
+    fn parse_next_instruction(&mut self) -> Result<bool, SchematicError> {
         loop {
-            let command_res = self.read_var_int();
-            let command = match command_res {
-                Ok(c) => c,
-                Err(_) => return Ok(false),
+            let first_byte = match self.read_byte_or_eof()? {
+                Some(byte) => byte,
+                None => return Ok(false),
             };
-            match command {
-                0 => {
-                    let _ = self.read_var_int()?;
-                    let state_str = self.read_string()?;
-                    let state = BlockState::from_string(state_str)
-                        .map_err(|e| format!("VXL: Parse error: {}", e))?;
-                    let id = (self.palette.len() as i32 + 1) * 2;
-                    // println!("VXL Command: AddPaletteEntry ID={} State={}", id, state);
-                    self.palette.insert(id, Rc::new(state));
-                }
-                1 => {
-                    let ref_id = self.read_var_int()?;
-                    let diff_str = self.read_string()?;
-                    let base = self.palette.get(&ref_id)
-                        .ok_or_else(|| format!("VXL: Missing Ref ID {}", ref_id))?;
-                    let state = base.update(diff_str)
-                        .map_err(|e| format!("VXL: Diff error: {}", e))?;
-                    let id = (self.palette.len() as i32 + 1) * 2;
-                    self.palette.insert(id, Rc::new(state));
-                }
-                cmd => {
-                    let is_rle = (cmd & 1) != 0;
-                    let id = if is_rle { cmd - 1 } else { cmd };
-                    let length = if is_rle { self.read_var_int()? } else { 1 };
-                    let state = self.palette.get(&id)
-                        .cloned()
-                        .ok_or_else(|| format!("VXL: Unknown Palette ID {}", id))?;
-                    self.current_run_state = Some(state);
-                    self.remaining_run_length = length;
-                    return Ok(true);
+            match self.parse_instruction(first_byte) {
+                Ok(true) => return Ok(true),
+                Ok(false) => continue,
+                Err(SchematicError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return Err(SchematicError::Parse(format!("VXL: Stream truncated mid-instruction after {} blocks", self.read_blocks)));
                 }
+                Err(e) => return Err(e),
             }
         }
     }
 
-    fn read_var_int(&mut self) -> Result<i32, String> {
-        let mut num = 0;
-        let mut shift = 0;
-        let mut buf = [0u8; 1];
-        loop {
-            self.reader.read_exact(&mut buf).map_err(|e| e.to_string())?;
-            let byte = buf[0];
-            num |= ((byte & 0x7F) as i32) << shift;
-            if (byte & 0x80) == 0 { return Ok(num); }
-            shift += 7;
-            if shift >= 32 { return Err("VXL: VarInt too big".into()); }
+    /// Parses one instruction whose first byte was already read.
+    /// Returns `true` once a run was loaded and `false` for palette entries.
+    fn parse_instruction(&mut self, first_byte: u8) -> Result<bool, SchematicError> {
+        match self.continue_var_int(first_byte)? {
+            0 => {
+                self.check_palette_capacity()?;
+                let _ = self.read_var_int()?;
+                let state_str = self.read_string()?;
+                let state = BlockState::from_string(state_str)
+                    .map_err(|e| SchematicError::Parse(format!("VXL: Parse error: {}", e)))?;
+                let id = (self.palette.len() as i32 + 1) * 2;
+                // println!("VXL Command: AddPaletteEntry ID={} State={}", id, state);
+                self.palette.insert(id, Rc::new(state));
+                Ok(false)
+            }
+            1 => {
+                self.check_palette_capacity()?;
+                let ref_id = self.read_var_int()?;
+                let diff_str = self.read_string()?;
+                let base = self.palette.get(&ref_id)
+                    .ok_or_else(|| SchematicError::Parse(format!("VXL: Missing Ref ID {}", ref_id)))?;
+                let state = base.update(diff_str)
+                    .map_err(|e| SchematicError::Parse(format!("VXL: Diff error: {}", e)))?;
+                let id = (self.palette.len() as i32 + 1) * 2;
+                self.palette.insert(id, Rc::new(state));
+                Ok(false)
+            }
+            cmd => {
+                let is_rle = (cmd & 1) != 0;
+                let id = if is_rle { cmd - 1 } else { cmd };
+                let length = if is_rle { self.read_var_int()? } else { 1 };
+                let state = self.palette.get(&id)
+                    .cloned()
+                    .ok_or_else(|| SchematicError::Parse(format!("VXL: Unknown Palette ID {}", id)))?;
+                self.current_run_state = Some(state);
+                self.remaining_run_length = length;
+                Ok(true)
+            }
         }
     }
 
-    fn read_var_long(&mut self) -> Result<i64, String> {
-        let mut num = 0;
-        let mut shift = 0;
+    fn read_byte_or_eof(&mut self) -> Result<Option<u8>, SchematicError> {
         let mut buf = [0u8; 1];
         loop {
-            self.reader.read_exact(&mut buf).map_err(|e| e.to_string())?;
-            let byte = buf[0];
-            num |= ((byte & 0x7F) as i64) << shift;
-            if (byte & 0x80) == 0 { return Ok(num); }
-            shift += 7;
-            if shift >= 64 { return Err("VXL: VarLong too big".into()); }
+            match self.reader.read(&mut buf) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(buf[0])),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
         }
     }
 
-    fn read_string(&mut self) -> Result<String, String> {
+    fn read_var_int(&mut self) -> Result<i32, SchematicError> {
+        varint::read_var_int(&mut self.reader)
+    }
+
+    fn continue_var_int(&mut self, first_byte: u8) -> Result<i32, SchematicError> {
+        varint::read_var_int(&mut [first_byte].chain(&mut self.reader))
+    }
+
+    fn read_var_long(&mut self) -> Result<i64, SchematicError> {
+        varint::read_var_long(&mut self.reader)
+    }
+
+    fn read_string(&mut self) -> Result<String, SchematicError> {
         let len = self.read_var_int()?;
-        if len < 0 { return Err("Negative string length".into()); }
+        if len < 0 { return Err(SchematicError::Parse("VXL: Negative string length".into())); }
+        if len as usize > self.max_string_length {
+            return Err(SchematicError::Parse(format!("VXL: String length {} exceeds the maximum of {}", len, self.max_string_length)));
+        }
         let mut buf = vec![0u8; len as usize];
-        self.reader.read_exact(&mut buf).map_err(|e| e.to_string())?;
-        String::from_utf8(buf).map_err(|e| e.to_string())
+        self.reader.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|e| SchematicError::Parse(format!("VXL: Invalid UTF-8 string: {}", e)))
     }
 
-    fn read_boundary(&mut self) -> Result<Boundary, String> {
+    fn read_boundary(&mut self) -> Result<Boundary, SchematicError> {
         let min_x = self.read_var_int()?;
         let min_y = self.read_var_int()?;
         let min_z = self.read_var_int()?;
         let max_x = self.read_var_int()?;
         let max_y = self.read_var_int()?;
         let max_z = self.read_var_int()?;
-        Ok(Boundary::new_from_min_max(min_x, min_y, min_z, max_x, max_y, max_z))
+        Boundary::try_new_from_min_max(min_x, min_y, min_z, max_x, max_y, max_z)
+            .map_err(|e| SchematicError::Parse(format!("VXL: {}", e)))
     }
 
-    fn read_axis_order(&mut self) -> Result<AxisOrder, String> {
+    fn read_axis_order(&mut self) -> Result<AxisOrder, SchematicError> {
         let mut buf = [0u8; 1];
-        self.reader.read_exact(&mut buf).map_err(|e| e.to_string())?;
+        self.reader.read_exact(&mut buf)?;
         match buf[0] {
             0 => Ok(AxisOrder::XYZ),
             1 => Ok(AxisOrder::XZY),
@@ -207,7 +307,7 @@ impl<R: Read> VXLSchematicInputStream<R> {
             3 => Ok(AxisOrder::YZX),
             4 => Ok(AxisOrder::ZXY),
             5 => Ok(AxisOrder::ZYX),
-            n => Err(format!("VXL: Invalid AxisOrder {}", n)),
+            n => Err(SchematicError::Parse(format!("VXL: Invalid AxisOrder {}", n))),
         }
     }
 }
@@ -215,8 +315,10 @@ impl<R: Read> VXLSchematicInputStream<R> {
 #[cfg(test)]
 mod tests {
     use super::VXLSchematicInputStream;
-    use crate::common::{AxisOrder, Block, BlockState, Boundary, Region};
-    use crate::stream::stream::SchematicInputStream;
+    use crate::stream::error::SchematicError;
+    use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
+    use crate::stream::stream::{SchematicInputStream, SchematicOutputStream};
+    use crate::stream::vxl_writer::VXLSchematicOutputStream;
     use std::io::Cursor;
     use std::rc::Rc;
 
@@ -241,7 +343,7 @@ mod tests {
         let boundary = Boundary::new_from_size(2, 1, 3);
         let expected_blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
             .zip(blocks_states.iter())
-            .map(|(pos, state)| Block { position: pos, state: Rc::clone(state) })
+            .map(|(pos, state)| Block { position: pos, state: Rc::clone(state), biome: None })
             .collect();
 
         let expected_blocks: Vec<Block> = expected_blocks.into_iter()
@@ -258,4 +360,198 @@ mod tests {
             panic!("Failed to read blocks from VXL stream: {:?}", result);
         }
     }
-}
\ No newline at end of file
+
+    fn write_with_metadata(metadata: Option<&str>) -> (Vec<u8>, Vec<Block>) {
+        let boundary = Boundary::new_from_size(3, 2, 2);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .step_by(2)
+            .map(|pos| Block::new(stone.clone(), pos))
+            .collect();
+        let mut buffer = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut buffer, AxisOrder::XYZ, boundary);
+        if let Some(metadata) = metadata {
+            writer.set_metadata(metadata.to_string()).unwrap();
+        }
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+        (buffer, blocks)
+    }
+
+    #[test]
+    fn test_vxl_metadata_round_trip() {
+        let metadata = r#"{"author":"richy","name":"test"}"#;
+        let (bytes, expected) = write_with_metadata(Some(metadata));
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(bytes));
+        assert_eq!(reader.boundary().unwrap(), Some(Boundary::new_from_size(3, 2, 2)));
+        assert_eq!(reader.metadata(), Some(metadata.to_string()));
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_vxl_without_metadata_round_trip() {
+        let (bytes, expected) = write_with_metadata(None);
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(bytes));
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), expected);
+        assert_eq!(reader.metadata(), None);
+    }
+
+    #[test]
+    fn test_vxl_empty_metadata_round_trip() {
+        let (bytes, _) = write_with_metadata(Some(""));
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(bytes));
+        reader.boundary().unwrap();
+        assert_eq!(reader.metadata(), Some(String::new()));
+    }
+
+    #[test]
+    fn test_negative_boundary_round_trip() {
+        let boundary = Boundary::new(-64, -64, -2147483648, 3, 2, 2);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .step_by(3)
+            .map(|pos| Block::new(stone.clone(), pos))
+            .collect();
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, AxisOrder::XYZ, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(data));
+        assert_eq!(reader.boundary().unwrap(), Some(boundary));
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), blocks);
+    }
+
+    #[test]
+    fn test_bad_magic_is_a_parse_error() {
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(vec![0x01, 0x02, 0x03]));
+        assert!(matches!(reader.boundary(), Err(SchematicError::Parse(_))));
+
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(vec![205, 164, 145, 226, 132, 203, 21, 99]));
+        assert!(matches!(reader.boundary(), Err(SchematicError::UnsupportedVersion(99))));
+    }
+
+    #[test]
+    fn test_truncated_header_is_an_io_error() {
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(vec![0xCD, 0xA4]));
+        assert!(matches!(reader.boundary(), Err(SchematicError::Io(_))));
+    }
+
+    #[test]
+    fn test_long_air_runs_are_skipped() {
+        // 2^32 cells, far too many to walk one by one, almost all of them air
+        let boundary = Boundary::new_from_size(1 << 16, 1 << 8, 1 << 8);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks: Vec<Block> = [
+            BlockPosition::new(0, 0, 0),
+            BlockPosition::new(1 << 15, 1 << 7, 1 << 7),
+            BlockPosition::new((1 << 16) - 1, (1 << 8) - 1, (1 << 8) - 1),
+        ].into_iter()
+            .map(|pos| Block::new(stone.clone(), pos))
+            .collect();
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, AxisOrder::XYZ, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(data));
+        let mut read = Vec::new();
+        while let Some(chunk) = reader.read_next(1).unwrap() {
+            assert_eq!(chunk.len(), 1);
+            read.extend(chunk);
+            let expected_cursor = AxisOrder::XYZ.index(&read.last().unwrap().position, &boundary) as usize + 1;
+            assert_eq!(reader.read_blocks, expected_cursor);
+        }
+        assert_eq!(read, blocks);
+        assert_eq!(reader.read_blocks, boundary.volume());
+    }
+
+    #[test]
+    fn test_seek_forward_within_runs() {
+        let boundary = Boundary::new_from_size(4, 3, 2);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let dirt = Rc::new(BlockState::from_str("minecraft:dirt").unwrap());
+        // runs of three equal cells, so most seek targets land inside a run
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .enumerate()
+            .filter(|(index, _)| index % 9 != 8)
+            .map(|(index, pos)| Block::new(if index / 3 % 2 == 0 { stone.clone() } else { dirt.clone() }, pos))
+            .collect();
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, AxisOrder::XYZ, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(data));
+        reader.seek_to(7).unwrap();
+        let expected: Vec<Block> = blocks.iter()
+            .filter(|block| AxisOrder::XYZ.index(&block.position, &boundary) >= 7)
+            .cloned()
+            .collect();
+        assert_eq!(reader.read_next(1).unwrap().unwrap(), expected[..1]);
+        assert!(matches!(reader.seek_to(4), Err(SchematicError::OutOfBounds(_))));
+        assert!(matches!(reader.seek_to(boundary.volume() + 1), Err(SchematicError::OutOfBounds(_))));
+        assert_eq!(reader.read_to_end_into_vec().unwrap(), expected[1..]);
+    }
+
+    #[test]
+    fn test_palette_snapshot() {
+        let vxl_data: Vec<u8> = vec![205,164,145,226,132,203,21,1,0,0,0,1,0,2,0,0,0,15,109,105,110,101,99,114,97,102,116,58,97,105,114,91,93,3,3,1,2,15,109,105,110,101,99,114,97,102,116,58,115,116,111,110,101,5,2,2];
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(vxl_data));
+        assert!(reader.palette_snapshot().is_empty());
+        reader.read_to_end_into_vec().unwrap();
+        let palette = reader.palette_snapshot();
+        assert_eq!(palette.len(), 2);
+        assert!(palette[0].is_air());
+        assert_eq!(*palette[1], BlockState::from_str("minecraft:stone").unwrap());
+    }
+
+    #[test]
+    fn test_truncated_run_is_an_error() {
+        let vxl_data: Vec<u8> = vec![205,164,145,226,132,203,21,1,0,0,0,1,0,2,0,0,0,15,109,105,110,101,99,114,97,102,116,58,97,105,114,91,93,3,3,1,2,15,109,105,110,101,99,114,97,102,116,58,115,116,111,110,101,5,2,2];
+
+        // cut between the stone run command and its run length
+        let mid_run = vxl_data[..vxl_data.len() - 2].to_vec();
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(mid_run));
+        match reader.read_to_end_into_vec() {
+            Err(SchematicError::Parse(msg)) => assert!(msg.contains("truncated"), "{}", msg),
+            other => panic!("Expected a truncation error, got {:?}", other),
+        }
+
+        // cut exactly at an instruction boundary still ends cleanly
+        let at_boundary = vxl_data[..vxl_data.len() - 1].to_vec();
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(at_boundary));
+        assert_eq!(reader.read_to_end_into_vec().unwrap().len(), 2);
+    }
+
+    fn header_bytes() -> Vec<u8> {
+        // magic, version 1, boundary (0,0,0)-(1,0,2), axis order XYZ
+        vec![205, 164, 145, 226, 132, 203, 21, 1, 0, 0, 0, 1, 0, 2, 0]
+    }
+
+    #[test]
+    fn test_oversized_string_length_is_rejected() {
+        let mut data = header_bytes();
+        // add palette entry with a declared string length of 1_000_000 bytes
+        data.extend([0, 0, 0xC0, 0x84, 0x3D]);
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(data));
+        let error = reader.read_to_end_into_vec().unwrap_err();
+        assert!(error.to_string().contains("exceeds the maximum"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn test_palette_limit_is_enforced() {
+        let mut data = header_bytes();
+        for name in ["minecraft:stone", "minecraft:dirt"] {
+            data.extend([0, 0, name.len() as u8]);
+            data.extend(name.as_bytes());
+        }
+        data.push(2);
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(data.clone()));
+        reader.set_max_palette_entries(1);
+        assert!(matches!(reader.read_to_end_into_vec(), Err(SchematicError::Parse(_))));
+
+        let mut reader = VXLSchematicInputStream::new(Cursor::new(data));
+        assert_eq!(reader.read_to_end_into_vec().unwrap().len(), 1);
+    }
+}
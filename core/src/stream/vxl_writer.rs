@@ -1,5 +1,8 @@
 use crate::common::{AxisOrder, Block, BlockState, Boundary};
+use crate::stream::error::SchematicError;
 use crate::stream::stream::SchematicOutputStream;
+use crate::stream::varint;
+use crate::stream::vxl_reader::DEFAULT_MAX_STRING_LENGTH;
 use std::collections::HashMap;
 use std::io::Write;
 use std::rc::Rc;
@@ -7,27 +10,62 @@ use std::sync::Arc;
 
 const MAGIC_NUMBER: i64 = 0x56584C44524D; // "VXLDRM"
 const VERSION: i32 = 1;
+const METADATA_VERSION: i32 = 2;
 
 pub struct VXLSchematicOutputStream<W: Write> {
     writer: W,
     running_palette: HashMap<Rc<BlockState>, i32>,
+    /// Palette states grouped by block name, in the order they were added.
+    states_by_name: HashMap<String, Vec<Rc<BlockState>>>,
+    /// The first palette state of every block name, the diff candidates for a name not seen yet.
+    first_of_each_name: Vec<Rc<BlockState>>,
     header_written: bool,
     closed: bool,
     axis_order: AxisOrder,
     boundary: Boundary,
-    written_blocks: usize
+    written_blocks: usize,
+    metadata: Option<String>,
+    buffer: Option<Vec<Block>>,
+    disable_diff: bool,
 }
 
 impl<W: Write> SchematicOutputStream for VXLSchematicOutputStream<W> {
-    fn write(&mut self, blocks: &[Block]) -> Result<usize, String> {
+    fn write(&mut self, blocks: &[Block]) -> Result<usize, SchematicError> {
+        if let Some(buffer) = self.buffer.as_mut() {
+            buffer.extend_from_slice(blocks);
+            return Ok(blocks.len());
+        }
         if !self.header_written {
             let boundary = Arc::new(self.boundary);
             self.write_header(Arc::clone(&boundary))?;
         }
-        self.write_blocks(blocks)
+        self.write_blocks(blocks).map_err(SchematicError::from)
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) -> Result<(), SchematicError> {
+        if self.header_written {
+            return Err("VXL: Boundary must be set before the header is written".into());
+        }
+        self.boundary = boundary;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SchematicError> {
+        self.writer.flush().map_err(SchematicError::Io)
     }
 
-    fn complete(&mut self) -> Result<(), String> {
+    fn complete(&mut self) -> Result<(), SchematicError> {
+        if let Some(mut buffered) = self.buffer.take() {
+            // latest write wins: reverse first so the stable sort keeps it in front of older duplicates
+            buffered.reverse();
+            let axis_order = self.axis_order;
+            let boundary = self.boundary;
+            buffered.sort_by_key(|block| axis_order.index(&block.position, &boundary));
+            buffered.dedup_by_key(|block| block.position);
+            if !buffered.is_empty() {
+                self.write(&buffered)?;
+            }
+        }
         self.writer.flush().map_err(|e| e.to_string())?;
         self.closed = true;
         Ok(())
@@ -39,11 +77,52 @@ impl<W: Write> VXLSchematicOutputStream<W> {
         Self {
             writer,
             running_palette: HashMap::new(),
+            states_by_name: HashMap::new(),
+            first_of_each_name: Vec::new(),
             header_written: false,
             closed: false,
             axis_order, boundary,
-            written_blocks: 0
+            written_blocks: 0,
+            metadata: None,
+            buffer: None,
+            disable_diff: false,
+        }
+    }
+
+    /// Like `new`, but accepts blocks in any order.
+    /// Everything written is held in memory until `complete()` sorts it and encodes the file,
+    /// so memory grows with the number of blocks instead of staying constant.
+    /// If a position is written twice, the later block wins.
+    pub fn new_buffered(writer: W, axis_order: AxisOrder, boundary: Boundary) -> Self {
+        Self {
+            buffer: Some(Vec::new()),
+            ..Self::new(writer, axis_order, boundary)
+        }
+    }
+
+    /// Writes every new palette state in full instead of as a diff against the closest known state.
+    /// The palette then reads in the order states first appear, at the cost of a larger file.
+    /// Defaults to false.
+    pub fn set_disable_diff(&mut self, disable_diff: bool) {
+        self.disable_diff = disable_diff;
+    }
+
+    /// Attaches a metadata string (usually JSON with author, name, timestamp, ...) to the output.
+    /// Files with metadata are written as version 2, files without stay at version 1.
+    /// Must be called before the first block is written. Metadata longer than
+    /// `DEFAULT_MAX_STRING_LENGTH` bytes is rejected, since readers would refuse it by default.
+    pub fn set_metadata(&mut self, metadata: String) -> Result<(), String> {
+        if self.header_written {
+            return Err("VXL: Metadata must be set before the header is written".into());
+        }
+        if metadata.len() > DEFAULT_MAX_STRING_LENGTH {
+            return Err(format!(
+                "VXL: Metadata of {} bytes exceeds the maximum of {}",
+                metadata.len(), DEFAULT_MAX_STRING_LENGTH
+            ));
         }
+        self.metadata = Some(metadata);
+        Ok(())
     }
 
     pub fn write_header(&mut self, boundary: Arc<Boundary>) -> Result<(), String> {
@@ -51,19 +130,36 @@ impl<W: Write> VXLSchematicOutputStream<W> {
             return Err("VXL: Header already written".into());
         }
         self.write_var_long(MAGIC_NUMBER);
-        self.write_var_int(VERSION);
+        let version = if self.metadata.is_some() { METADATA_VERSION } else { VERSION };
+        self.write_var_int(version);
         self.write_boundary(&boundary)?;
         self.write_axis_order(self.axis_order)?;
+        if let Some(metadata) = self.metadata.clone() {
+            self.write_string(&metadata)?;
+        }
         self.header_written = true;
         Ok(())
     }
 
+    /// Only states with the same name are compared, as those diffs are the ones that skip the name.
+    /// A new name is compared against one state per known name. This keeps a write at
+    /// O(states × states of one name) instead of O(states²) for palettes with thousands of states.
     fn find_closest_state(&self, new_state: &BlockState) -> Option<Rc<BlockState>> {
-        self.running_palette.keys()
+        let candidates = self.states_by_name.get(new_state.name_ref()).unwrap_or(&self.first_of_each_name);
+        candidates.iter()
             .min_by_key(|state| state.difference(new_state).len())
             .cloned()
     }
 
+    fn add_to_palette(&mut self, state: &Rc<BlockState>, id: i32) {
+        self.running_palette.insert(Rc::clone(state), id);
+        let same_name = self.states_by_name.entry(state.name()).or_default();
+        if same_name.is_empty() {
+            self.first_of_each_name.push(Rc::clone(state));
+        }
+        same_name.push(Rc::clone(state));
+    }
+
     pub fn write_blocks(&mut self, blocks: &[Block]) -> Result<usize, String> {
         if !self.header_written {
             return Err("VXL: Header must be written before blocks".into());
@@ -86,10 +182,15 @@ impl<W: Write> VXLSchematicOutputStream<W> {
                 ));
             }
             if flat_index > self.written_blocks {
-                let gap = flat_index - self.written_blocks;
+                let mut gap = flat_index - self.written_blocks;
                 let air = BlockState::air_rc();
-                self.write_palette_id_with_rle(&air, gap as i32)?;
-                self.written_blocks += gap;
+                // runs are stored as i32, so huge gaps are split up
+                while gap > 0 {
+                    let run = gap.min(i32::MAX as usize);
+                    self.write_palette_id_with_rle(&air, run as i32)?;
+                    self.written_blocks += run;
+                    gap -= run;
+                }
             }
             let mut run_length = 0;
             let start_cursor = self.written_blocks;
@@ -135,7 +236,7 @@ impl<W: Write> VXLSchematicOutputStream<W> {
             return Ok(id);
         }
         let new_id = (self.running_palette.len() as i32 + 1) * 2;
-        if self.running_palette.is_empty() {
+        if self.running_palette.is_empty() || self.disable_diff {
             self.write_var_int(0);
             self.write_var_int(0);
             self.write_string(&state.to_string())?;
@@ -147,40 +248,18 @@ impl<W: Write> VXLSchematicOutputStream<W> {
             self.write_var_int(closest_id);
             self.write_string(&diff_str)?;
         }
-        self.running_palette.insert(Rc::clone(state), new_id);
+        self.add_to_palette(state, new_id);
         Ok(new_id)
     }
 }
 
 impl<W: Write> VXLSchematicOutputStream<W> {
-    fn write_var_int(&mut self, mut value: i32) {
-        let mut buf = [0u8; 5];
-        let mut pos = 0;
-        loop {
-            if (value & !0x7F) == 0 {
-                buf[pos] = value as u8;
-                self.writer.write_all(&buf[..pos + 1]).expect("Write failed");
-                return;
-            }
-            buf[pos] = ((value & 0x7F) | 0x80) as u8;
-            value >>= 7;
-            pos += 1;
-        }
+    fn write_var_int(&mut self, value: i32) {
+        varint::write_var_int(&mut self.writer, value).expect("Write failed");
     }
 
-    fn write_var_long(&mut self, mut value: i64) {
-        let mut buf = [0u8; 10];
-        let mut pos = 0;
-        loop {
-            if (value & !0x7F) == 0 {
-                buf[pos] = value as u8;
-                self.writer.write_all(&buf[..pos + 1]).expect("Write failed");
-                return;
-            }
-            buf[pos] = ((value & 0x7F) | 0x80) as u8;
-            value >>= 7;
-            pos += 1;
-        }
+    fn write_var_long(&mut self, value: i64) {
+        varint::write_var_long(&mut self.writer, value).expect("Write failed");
     }
 
     fn write_string(&mut self, value: &str) -> Result<(), String> {
@@ -215,9 +294,13 @@ impl<W: Write> VXLSchematicOutputStream<W> {
 
 #[cfg(test)]
 mod test {
-    use crate::common::{AxisOrder, Block, BlockState, Boundary, Region};
-    use crate::stream::stream::SchematicOutputStream;
-    use std::io::{Cursor, Read};
+    use crate::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region};
+    use crate::stream::stream::{SchematicInputStream, SchematicOutputStream, SharedBuffer};
+    use crate::stream::varint::{read_var_int, read_var_long};
+    use crate::stream::vxl_reader::{VXLSchematicInputStream, DEFAULT_MAX_STRING_LENGTH};
+    use crate::stream::vxl_writer::VXLSchematicOutputStream;
+    use std::cell::RefCell;
+    use std::io::{BufWriter, Cursor, Read};
     use std::rc::Rc;
     use std::sync::Arc;
 
@@ -242,6 +325,7 @@ mod test {
                 state_opt.map(|state| Block {
                     position: pos,
                     state,
+                    biome: None,
                 })
             })
             .collect();
@@ -327,44 +411,145 @@ mod test {
         assert_eq!(final_air_command, 2); // id 2 for air without RLE
     }
 
-    fn read_string(reader: &mut dyn Read) -> Result<String, String> {
-        let len = read_var_int(reader)?;
-        if len < 0 { return Err("Negative string length".into()); }
-        let mut buf = vec![0u8; len as usize];
-        reader.read_exact(&mut buf).map_err(|e| e.to_string())?;
-        String::from_utf8(buf).map_err(|e| e.to_string())
+    #[test]
+    fn test_oversized_metadata_is_rejected() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut buffer, AxisOrder::XYZ, Boundary::new_from_size(1, 1, 1));
+        assert!(writer.set_metadata("x".repeat(DEFAULT_MAX_STRING_LENGTH + 1)).is_err());
+        writer.set_metadata("x".repeat(DEFAULT_MAX_STRING_LENGTH)).unwrap();
     }
 
-    fn read_var_int(reader: &mut dyn Read) -> Result<i32, String> {
-        let mut result: i32 = 0;
-        let mut shift = 0;
-        loop {
-            let mut byte = [0u8; 1];
-            reader.read_exact(&mut byte).map_err(|e| e.to_string())?;
-            let byte_val = byte[0] as i32;
-            result |= (byte_val & 0x7F) << shift;
-            if (byte_val & 0x80) == 0 {
-                break;
+    #[test]
+    fn test_disable_diff_writes_full_states() {
+        let boundary = Boundary::new_from_size(4, 2, 1);
+        let states: Vec<Rc<BlockState>> = ["minecraft:stone", "minecraft:oak_log[axis=x]", "minecraft:oak_log[axis=y]", "minecraft:glass"]
+            .iter()
+            .map(|name| Rc::new(BlockState::from_str(name).unwrap()))
+            .collect();
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .enumerate()
+            .map(|(i, pos)| Block::new(states[i % states.len()].clone(), pos))
+            .collect();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut buffer, AxisOrder::XYZ, boundary);
+        writer.set_disable_diff(true);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut cursor = Cursor::new(&buffer);
+        read_var_long(&mut cursor).unwrap();
+        for _ in 0..7 {
+            read_var_int(&mut cursor).unwrap();
+        }
+        cursor.read_exact(&mut [0u8; 1]).unwrap();
+        let mut full_states = Vec::new();
+        while (cursor.position() as usize) < buffer.len() {
+            match read_var_int(&mut cursor).unwrap() {
+                0 => {
+                    read_var_int(&mut cursor).unwrap();
+                    full_states.push(read_string(&mut cursor).unwrap());
+                }
+                1 => panic!("Found a diff command with diffs disabled"),
+                id if id % 2 == 1 => {
+                    read_var_int(&mut cursor).unwrap();
+                }
+                _ => {}
             }
-            shift += 7;
         }
-        Ok(result)
+        let expected: Vec<String> = states.iter().map(|state| state.to_string()).collect();
+        assert_eq!(full_states, expected);
+
+        let read = VXLSchematicInputStream::new(buffer.as_slice()).read_to_end_into_vec().unwrap();
+        assert_eq!(read, blocks);
     }
 
-    fn read_var_long(reader: &mut dyn Read) -> Result<i64, String> {
-        let mut result: i64 = 0;
-        let mut shift = 0;
-        loop {
-            let mut byte = [0u8; 1];
-            reader.read_exact(&mut byte).map_err(|e| e.to_string())?;
-            let byte_val = byte[0] as i64;
-            result |= (byte_val & 0x7F) << shift;
-            if (byte_val & 0x80) == 0 {
-                break;
-            }
-            shift += 7;
-        }
-        Ok(result)
+    #[test]
+    fn test_buffered_writer_accepts_reverse_order() {
+        let boundary = Boundary::new(2, 0, 3, 4, 3, 2);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let glass = Rc::new(BlockState::from_str("minecraft:glass").unwrap());
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .enumerate()
+            .filter(|(i, _)| i % 3 != 0)
+            .map(|(i, pos)| Block::new(if i % 2 == 0 { stone.clone() } else { glass.clone() }, pos))
+            .collect();
+
+        let mut ordered = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut ordered, AxisOrder::XYZ, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let mut reversed_blocks = blocks.clone();
+        reversed_blocks.reverse();
+        let mut buffered = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new_buffered(&mut buffered, AxisOrder::XYZ, boundary);
+        writer.write(&reversed_blocks[..5]).unwrap();
+        writer.write(&[Block::new(glass.clone(), BlockPosition::new(2, 0, 3))]).unwrap();
+        writer.write(&reversed_blocks[5..]).unwrap();
+        writer.complete().unwrap();
+
+        let from_ordered = VXLSchematicInputStream::new(ordered.as_slice()).read_to_end_into_vec().unwrap();
+        let mut from_buffered = VXLSchematicInputStream::new(buffered.as_slice()).read_to_end_into_vec().unwrap();
+        assert_eq!(from_buffered.remove(0), Block::new(glass, BlockPosition::new(2, 0, 3)));
+        assert_eq!(from_buffered, from_ordered);
+        assert_eq!(from_ordered, blocks);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_many_distinct_states_round_trip() {
+        let boundary = Boundary::new_from_size(40, 10, 10);
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ)
+            .enumerate()
+            .map(|(i, pos)| {
+                let state = if i % 3 == 0 {
+                    format!("minecraft:block_{}", i % 97)
+                } else {
+                    format!("minecraft:block_{}[level={},lit={}]", i % 23, i % 211, i % 2 == 0)
+                };
+                Block::from_state_str(&state, pos).unwrap()
+            })
+            .collect();
+        let mut data = Vec::new();
+        let mut writer = VXLSchematicOutputStream::new(&mut data, AxisOrder::XYZ, boundary);
+        writer.write(&blocks).unwrap();
+        writer.complete().unwrap();
+
+        let read = VXLSchematicInputStream::new(data.as_slice()).read_to_end_into_vec().unwrap();
+        assert_eq!(read, blocks);
+    }
+
+    #[test]
+    fn test_flush_pushes_bytes_mid_stream() {
+        let boundary = Boundary::new_from_size(4, 1, 1);
+        let stone = Rc::new(BlockState::from_str("minecraft:stone").unwrap());
+        let blocks: Vec<Block> = boundary.iter(AxisOrder::XYZ).map(|pos| Block::new(stone.clone(), pos)).collect();
+        let bytes = Rc::new(RefCell::new(Vec::new()));
+        let mut writer = VXLSchematicOutputStream::new(
+            BufWriter::new(SharedBuffer(bytes.clone())),
+            AxisOrder::XYZ,
+            boundary,
+        );
+
+        writer.write(&blocks[..2]).unwrap();
+        assert!(bytes.borrow().is_empty());
+        writer.flush().unwrap();
+        let flushed = bytes.borrow().len();
+        assert!(flushed > 0);
+
+        writer.write(&blocks[2..]).unwrap();
+        writer.complete().unwrap();
+        assert!(bytes.borrow().len() > flushed);
+        let read = VXLSchematicInputStream::new(bytes.borrow().as_slice()).read_to_end_into_vec().unwrap();
+        assert_eq!(read, blocks);
+    }
+
+    fn read_string(reader: &mut dyn Read) -> Result<String, String> {
+        let len = read_var_int(reader).map_err(|e| e.to_string())?;
+        if len < 0 { return Err("Negative string length".into()); }
+        let mut buf = vec![0u8; len as usize];
+        reader.read_exact(&mut buf).map_err(|e| e.to_string())?;
+        String::from_utf8(buf).map_err(|e| e.to_string())
+    }
+
+
+}
@@ -13,6 +13,12 @@ public class BlockInputStream implements AutoCloseable {
 
   public native Boundary boundary();
 
+  /**
+   * Returns the number of cells in the boundary, an upper bound for the blocks this stream yields.
+   * Useful to size arrays up front. Returns -1 if the format does not declare a boundary.
+   */
+  public native long blockCount();
+
   @Override
   public synchronized native void close();
   
@@ -12,6 +12,13 @@ public class BlockOutputStream implements AutoCloseable {
   // Usually doesn't write the blocks to disk yet
   public native void write(Block[] blocks, int offset, int length);
 
+  /**
+   * Writes blocks from flat primitive arrays, avoiding one {@link Block} object per entry.
+   * Block {@code i} is at {@code positions[3i], positions[3i + 1], positions[3i + 2]} (x, y, z)
+   * and has the state {@code paletteNames[stateIds[i]]}, a full block state string.
+   */
+  public native void writePacked(int[] positions, int[] stateIds, String[] paletteNames);
+
   // Usually flushes the blocks to disk
   @Override
   public synchronized native void close();
@@ -43,7 +43,17 @@ public class Voxels {
     return blocksToBytes(outputStream, schematicType, null);
   }
 
-  public static native BlockOutputStream blocksToBytes(OutputStream outputStream, SchematicType schematicType, Boundary boundary);
+  public static BlockOutputStream blocksToBytes(OutputStream outputStream, SchematicType schematicType, Boundary boundary) {
+    return blocksToBytes(outputStream, schematicType, boundary, "gzip");
+  }
+
+  /**
+   * Opens a block writer with the given container compression.
+   * Readers detect the compression from the magic bytes, so no matching option is needed when reading back.
+   *
+   * @param compressionAlgo one of "gzip", "zstd" or "none"
+   */
+  public static native BlockOutputStream blocksToBytes(OutputStream outputStream, SchematicType schematicType, Boundary boundary, String compressionAlgo);
 
   public static synchronized void initialize() {
     // No-op: just to ensure the static block is executed.
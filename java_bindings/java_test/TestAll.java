@@ -18,14 +18,29 @@ public class TestAll {
     File inFile = new File(BASE_PATH, "mojang.schem");
     try (InputStream is = new FileInputStream(inFile);
          BlockInputStream bis = Voxels.bytesToBlocks(is, SchematicType.MOJANG)) {
+      Boundary boundary = bis.boundary();
+      check(boundary != null, "Mojang boundary should be readable before any block");
+      long volume = (long) boundary.dX() * boundary.dY() * boundary.dZ();
+      check(bis.blockCount() == volume, "blockCount " + bis.blockCount() + " should match the boundary volume " + volume);
       Block[] buffer = new Block[512];
       int read;
       long totalRead = 0;
       while ((read = bis.read(buffer, 0, buffer.length)) != -1) {
         // Process blocks in buffer[0..read-1]
+        for (int i = 0; i < read; i++) {
+          // the buffer starts out empty, so every slot is a Block created from the cached class
+          check(buffer[i] != null && buffer[i].getClass() == Block.class, "Slot " + i + " was not filled with a Block");
+        }
         totalRead += read;
       }
       System.out.println("Total blocks read: " + totalRead);
+      check(totalRead <= volume, "Read " + totalRead + " blocks, more than the boundary holds");
+    }
+  }
+
+  private static void check(boolean condition, String message) {
+    if (!condition) {
+      throw new AssertionError(message);
     }
   }
 
@@ -3,7 +3,7 @@ mod jstreams;
 use robusta_jni::bridge;
 use robusta_jni::convert::{FromJavaValue, Signature, TryFromJavaValue, TryIntoJavaValue};
 use robusta_jni::jni::errors::Result as JniResult;
-use robusta_jni::jni::objects::{AutoLocal, JObject};
+use robusta_jni::jni::objects::{AutoLocal, JClass, JObject};
 use robusta_jni::jni::objects::{GlobalRef, JFieldID};
 use robusta_jni::jni::JNIEnv;
 use std::collections::HashMap;
@@ -150,17 +150,16 @@ mod jni {
     use std::io::{BufReader, BufWriter};
     use super::*;
     use crate::jstreams::{JavaInputStream, JavaOutputStream};
-    use flate2::Compression;
     use robusta_jni::convert::Field;
     use robusta_jni::jni::sys::jlong;
-    use voxels_core::common::{AxisOrder, Block};
+    use voxels_core::common::Block;
     use voxels_core::stream::any_reader::AnySchematicInputStream;
+    use voxels_core::stream::compression::{detect_decoder, CompressionAlgo};
+    use voxels_core::stream::file::output_stream_for;
+    use voxels_core::stream::format::SchematicFormat;
     use voxels_core::stream::mojang_reader::MojangSchematicInputStream;
-    use voxels_core::stream::mojang_writer::MojangSchematicOutputStream;
     use voxels_core::stream::sponge_reader::SpongeSchematicInputStream;
-    use voxels_core::stream::sponge_writer::SpongeSchematicOutputStream;
     use voxels_core::stream::vxl_reader::VXLSchematicInputStream;
-    use voxels_core::stream::vxl_writer::VXLSchematicOutputStream;
 
     #[derive(Signature, TryIntoJavaValue, TryFromJavaValue, FromJavaValue)]
     #[package(de.richy.voxels)]
@@ -188,26 +187,32 @@ mod jni {
             let stream = JavaInputStream::new(
                 env, input_stream,
             )?;
-            use flate2::read::GzDecoder;
+            let stream = match detect_decoder(BufReader::new(stream)) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    env.throw_new("java/io/IOException", e.to_string())?;
+                    return Ok(JObject::null());
+                }
+            };
             let sis: Box<dyn SchematicInputStream> = match schematic_type_str.as_str() {
                 "MOJANG" => {
                     Box::new(MojangSchematicInputStream::new(
-                        BufReader::new(GzDecoder::new(stream))
+                        BufReader::new(stream)
                     ))
                 },
                 "VXL" => {
                     Box::new(VXLSchematicInputStream::new(
-                        BufReader::new(GzDecoder::new(stream))
+                        BufReader::new(stream)
                     ))
                 },
                 "SPONGE" => {
                     Box::new(SpongeSchematicInputStream::new(
-                        BufReader::new(GzDecoder::new(stream))
+                        BufReader::new(stream)
                     ))
                 }
                 _ => {
                     Box::new(AnySchematicInputStream::new_from_known(
-                        BufReader::new(GzDecoder::new(stream))
+                        BufReader::new(stream)
                     ))
                 }
             };
@@ -226,7 +231,8 @@ mod jni {
             env: &JNIEnv<'env>,
             output_stream: JObject<'env>,
             schematic_type: JObject<'env>,
-            boundary: JObject<'env>
+            boundary: JObject<'env>,
+            compression_algo: JObject<'env>,
         ) -> JniResult<JObject<'env>> {
             if output_stream.is_null() {
                 env.throw_new("java/lang/NullPointerException", "Output stream is null")?;
@@ -244,40 +250,41 @@ mod jni {
             } else {
                 None
             };
-            use flate2::write::GzEncoder;
-            let sis: Box<dyn SchematicOutputStream> = match schematic_type_str.as_str() {
-                "MOJANG" => {
-                    Box::new(MojangSchematicOutputStream::new(GzEncoder::new(stream, Compression::default())))
-                },
-                "VXL" => {
-                    if boundary_r.is_none() {
-                        env.throw_new("java/lang/IllegalArgumentException", "Boundary must be provided for VXL schematic type")?;
-                        return Ok(JObject::null());
-                    }
-                    Box::new(VXLSchematicOutputStream::new(
-                        BufWriter::new(
-                            GzEncoder::new(stream, Compression::default())
-                            // stream
-                        ),
-                        AxisOrder::XYZ,
-                        boundary_r.unwrap()
-                    ))
-                },
-                "SPONGE" => {
-                    if boundary_r.is_none() {
-                        env.throw_new("java/lang/IllegalArgumentException", "Boundary must be provided for SPONGE schematic type")?;
+            let compression = if compression_algo.is_null() {
+                CompressionAlgo::Gzip
+            } else {
+                let name: String = env.get_string(compression_algo.into())?.into();
+                match CompressionAlgo::from_name(&name) {
+                    Ok(compression) => compression,
+                    Err(e) => {
+                        env.throw_new("java/lang/IllegalArgumentException", e.to_string())?;
                         return Ok(JObject::null());
                     }
-                    Box::new(SpongeSchematicOutputStream::new(
-                        GzEncoder::new(stream, Compression::default()),
-                        boundary_r.unwrap()
-                    ))
                 }
+            };
+            let stream = match compression.encoder(stream) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    env.throw_new("java/io/IOException", e.to_string())?;
+                    return Ok(JObject::null());
+                }
+            };
+            let format = match schematic_type_str.as_str() {
+                "MOJANG" => SchematicFormat::Mojang,
+                "VXL" => SchematicFormat::Vxl,
+                "SPONGE" => SchematicFormat::Sponge,
                 _ => {
                     env.throw_new("java/lang/IllegalArgumentException", "Unknown schematic type")?;
                     return Ok(JObject::null());
                 }
             };
+            let sis = match output_stream_for(BufWriter::new(stream), format, boundary_r) {
+                Ok(sis) => sis,
+                Err(e) => {
+                    env.throw_new("java/lang/IllegalArgumentException", e.to_string())?;
+                    return Ok(JObject::null());
+                }
+            };
             let boxedHandle = Box::new(
                 BlockOutputStreamHandle {
                     sos: sis,
@@ -356,7 +363,7 @@ mod jni {
                             let jposition = block.position.to_jni(env)?;
                             let jstate = handle.jni_cache.block_state_rust_to_java(env, &block.state)?;
                             let jni_block = env.new_object(
-                                env.find_class("de/richy/voxels/Block")?,
+                                JClass::from(handle.jni_cache.block_class.as_obj()),
                                 "(Lde/richy/voxels/BlockPosition;Lde/richy/voxels/BlockState;)V",
                                 &[jposition.into(), jstate.as_obj().into()],
                             )?;
@@ -416,6 +423,28 @@ mod jni {
             }
         }
 
+        /// Number of cells covered by the boundary, an upper bound for the blocks the stream yields.
+        /// Returns -1 if the format does not declare a boundary.
+        pub extern "jni" fn blockCount(
+            self, env: &JNIEnv<'env>,
+        ) -> JniResult<i64> {
+            let ptr_value = self.ptr.get()?;
+            if ptr_value == 0 {
+                env.throw_new("java/io/IOException", "Stream is closed")?;
+                return Ok(-1);
+            }
+            let ptr = ptr_value as *mut BlockInputStreamHandle;
+            let handle = unsafe { &mut *ptr };
+            match handle.sis.boundary() {
+                Ok(Some(boundary)) => Ok(boundary.volume() as i64),
+                Ok(None) => Ok(-1),
+                Err(e) => {
+                    env.throw_new("java/io/IOException", format!("Error getting block count: {}", e))?;
+                    Ok(-1)
+                }
+            }
+        }
+
         pub extern "jni" fn close(mut self) -> JniResult<()> {
             let ptr_value = self.ptr.get()?;
             // println!("BlockInputStream close called, ptr value: {}", ptr_value);
@@ -493,6 +522,83 @@ mod jni {
             }
         }
 
+        /// Writes blocks given as flat primitive arrays instead of `Block` objects.
+        /// `positions` holds `x, y, z` triples, one per block, so it must be three times as long as `state_ids`.
+        /// Each entry of `state_ids` indexes `palette_names`, which holds full block state strings.
+        pub extern "jni" fn writePacked(
+            self, env: &JNIEnv,
+            positions: JObject<'env>,
+            state_ids: JObject<'env>,
+            palette_names: JObject<'env>,
+        ) -> JniResult<()> {
+            let ptr_value = self.ptr.get()?;
+            if ptr_value == 0 {
+                env.throw_new("java/io/IOException", "Stream is closed")?;
+                return Ok(());
+            }
+            if positions.is_null() || state_ids.is_null() || palette_names.is_null() {
+                env.throw_new("java/lang/NullPointerException", "Positions, state ids and palette must not be null")?;
+                return Ok(());
+            }
+            let positions_len = env.get_array_length(positions.into_inner())? as usize;
+            let state_ids_len = env.get_array_length(state_ids.into_inner())? as usize;
+            if positions_len != state_ids_len * 3 {
+                env.throw_new(
+                    "java/lang/IllegalArgumentException",
+                    format!("Expected {} position components for {} state ids, got {}", state_ids_len * 3, state_ids_len, positions_len),
+                )?;
+                return Ok(());
+            }
+            let mut flat_positions = vec![0; positions_len];
+            env.get_int_array_region(positions.into_inner(), 0, &mut flat_positions)?;
+            let mut ids = vec![0; state_ids_len];
+            env.get_int_array_region(state_ids.into_inner(), 0, &mut ids)?;
+
+            let palette_len = env.get_array_length(palette_names.into_inner())?;
+            let mut palette = Vec::with_capacity(palette_len as usize);
+            for i in 0..palette_len {
+                let jname = env.get_object_array_element(palette_names.into_inner(), i)?;
+                if jname.is_null() {
+                    env.throw_new("java/lang/NullPointerException", format!("Palette entry {} is null", i))?;
+                    return Ok(());
+                }
+                let name: String = env.get_string(jname.into())?.into();
+                match BlockState::from_string(name) {
+                    Ok(state) => palette.push(Rc::new(state)),
+                    Err(e) => {
+                        env.throw_new("java/lang/IllegalArgumentException", format!("Invalid palette entry {}: {}", i, e))?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            let mut blocks: Vec<Block> = Vec::with_capacity(state_ids_len);
+            for (i, id) in ids.iter().enumerate() {
+                let state = match palette.get(*id as usize) {
+                    Some(state) if *id >= 0 => state.clone(),
+                    _ => {
+                        env.throw_new(
+                            "java/lang/IndexOutOfBoundsException",
+                            format!("State id {} at index {} is outside the palette of size {}", id, i, palette.len()),
+                        )?;
+                        return Ok(());
+                    }
+                };
+                let position = BlockPosition::new(flat_positions[i * 3], flat_positions[i * 3 + 1], flat_positions[i * 3 + 2]);
+                blocks.push(Block::new(state, position));
+            }
+
+            let ptr = ptr_value as *mut BlockOutputStreamHandle;
+            let handle = unsafe { &mut *ptr };
+            match handle.sos.write(&blocks) {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    env.throw_new("java/io/IOException", format!("Error writing blocks: {}", e))?;
+                    Ok(())
+                }
+            }
+        }
+
         pub extern "jni" fn close(
             mut self, env: &JNIEnv
         ) -> JniResult<()> {
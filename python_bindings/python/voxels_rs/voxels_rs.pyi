@@ -30,8 +30,21 @@ class VoxelReader:
   def __next__(self) -> list["PyBlock"]: ...
   def read_full(self) -> Any: ...
   def iter_bulks(self) -> Any: ...
-  def to_numpy(self) -> np.ndarray: ...
+  def estimated_total(self) -> Optional[int]: ...
+  def seek_to(self, block_index: int) -> None: ...
+  def iter_chunks(self, chunk_size: int) -> ChunkIterator: ...
+  def to_numpy(self) -> tuple[np.ndarray, list[str]]: ...
+  def save(self, output: Any, format: str = "vxl", compression_algo: str = "gzip") -> None: ...
   def close(self) -> Ten: ...
   def __str__(self) -> str: ...
 
-def open(input: Any, type: Any) -> VoxelReader: ...
\ No newline at end of file
+class ChunkIterator:
+  def __iter__(self) -> ChunkIterator: ...
+  def __next__(self) -> tuple[tuple[int, int, int], list[PyBlock]]: ...
+
+def open(input: Any, compression_algo: Optional[str] = None) -> VoxelReader: ...
+def from_numpy(
+  array: np.ndarray,
+  palette: list[str],
+  boundary_min: tuple[int, int, int] = (0, 0, 0),
+) -> VoxelReader: ...
@@ -1,22 +1,34 @@
 use std::fmt::format;
 use crate::pystream::{reader_from, writer_from};
 use crate::shared::{PyBlock, PyBoundary};
-use flate2::bufread::GzDecoder;
-use flate2::write::GzEncoder;
-use pyo3::exceptions::{PyRuntimeError, PyStopIteration};
+use pyo3::exceptions::{PyIOError, PyIndexError, PyRuntimeError, PyStopIteration, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::PyString;
+use numpy::{PyArray1, PyArray3, PyArrayDescrMethods, PyArrayMethods, PyUntypedArray, PyUntypedArrayMethods};
+use std::collections::{BTreeMap, HashMap};
 use std::io::{BufReader, BufWriter};
-use voxels_core::common::AxisOrder;
+use std::rc::Rc;
+use voxels_core::common::{AxisOrder, Block, BlockPosition, BlockState, Boundary, Region, Schematic};
 use voxels_core::stream::any_reader::AnySchematicInputStream;
+use voxels_core::stream::compression::{detect_decoder, CompressionAlgo};
+use voxels_core::stream::csv_writer::CsvSchematicOutputStream;
+use voxels_core::stream::error::SchematicError;
+use voxels_core::stream::file::output_stream_for;
+use voxels_core::stream::format::SchematicFormat;
 use voxels_core::stream::mojang_reader::MojangSchematicInputStream;
-use voxels_core::stream::mojang_writer::MojangSchematicOutputStream;
 use voxels_core::stream::sponge_reader::SpongeSchematicInputStream;
-use voxels_core::stream::sponge_writer::SpongeSchematicOutputStream;
 use voxels_core::stream::stream::SchematicInputStream;
-use voxels_core::stream::stream::SchematicOutputStream;
 use voxels_core::stream::vxl_reader::VXLSchematicInputStream;
-use voxels_core::stream::vxl_writer::VXLSchematicOutputStream;
+
+/// Maps core stream errors to the closest matching Python exception.
+fn schematic_error_to_py(e: SchematicError) -> PyErr {
+    match e {
+        SchematicError::Io(e) => PyErr::new::<PyIOError, _>(e.to_string()),
+        SchematicError::MalformedNbt(_) | SchematicError::UnsupportedVersion(_) => PyErr::new::<PyValueError, _>(e.to_string()),
+        SchematicError::OutOfBounds(_) => PyErr::new::<PyIndexError, _>(e.to_string()),
+        SchematicError::Parse(_) => PyErr::new::<PyRuntimeError, _>(e.to_string()),
+    }
+}
 
 #[pyclass(unsendable)]
 pub struct VoxelReader {
@@ -52,7 +64,7 @@ impl VoxelReader {
             return Err(PyErr::new::<PyRuntimeError, _>("Iterator already called"));
         }
         if let Some(reader) = &mut self.reader {
-            reader.read_next(1024).map_err(|e| PyErr::new::<PyRuntimeError, _>(e)).and_then(|opt| {
+            reader.read_next(1024).map_err(schematic_error_to_py).and_then(|opt| {
                 if let Some(blocks) = opt {
                     for block in blocks {
                         let py_block = PyBlock::from(block);
@@ -103,6 +115,22 @@ impl VoxelReader {
         Ok("voxels_rs.read() block".to_string())
     }
 
+    /// Upper bound for the number of cells, for progress bars. `None` if the format has no declared size.
+    fn estimated_total(&mut self) -> PyResult<Option<usize>> {
+        match &mut self.reader {
+            Some(reader) => reader.estimated_total().map_err(schematic_error_to_py),
+            None => Err(PyErr::new::<PyRuntimeError, _>("Reader is closed")),
+        }
+    }
+
+    /// Skips to the `block_index`-th cell of the boundary, counting air. Store-backed formats seek anywhere, VXL only forward.
+    fn seek_to(&mut self, block_index: usize) -> PyResult<()> {
+        match &mut self.reader {
+            Some(reader) => reader.seek_to(block_index).map_err(schematic_error_to_py),
+            None => Err(PyErr::new::<PyRuntimeError, _>("Reader is closed")),
+        }
+    }
+
     fn boundary(&mut self) -> PyResult<PyBoundary> {
         if !self.entered {
             return Err(PyErr::new::<PyRuntimeError, _>("Cannot get boundary without entering context"));
@@ -113,7 +141,7 @@ impl VoxelReader {
         if let Some(reader) = &mut self.reader {
             let result = reader.boundary();
             if let Err(e) = result {
-                Err(PyErr::new::<PyRuntimeError, _>(e))
+                Err(schematic_error_to_py(e))
             } else {
                 if let Some(boundary) = result.unwrap() {
                     Ok(PyBoundary::from(boundary))
@@ -166,7 +194,7 @@ impl VoxelReader {
             return Err(PyErr::new::<PyRuntimeError, _>("Iterator not initialized, call iter_bulks() first"));
         }
         if let Some(reader) = &mut self.reader {
-            reader.read_next(1024 * 8).map_err(|e| PyErr::new::<PyRuntimeError, _>(e)).and_then(|opt| {
+            reader.read_next(1024 * 8).map_err(schematic_error_to_py).and_then(|opt| {
                 if let Some(blocks) = opt {
                     Ok(blocks.into_iter().map(|b| {
                         PyBlock::from(b)
@@ -180,6 +208,72 @@ impl VoxelReader {
         }
     }
 
+    /// Groups all blocks by their `chunk_size`-aligned cube and iterates `((cx, cy, cz), blocks)` pairs.
+    /// A chunk is only complete once the whole stream was read, so everything is buffered up front.
+    fn iter_chunks(&mut self, chunk_size: i32) -> PyResult<ChunkIterator> {
+        if !self.entered {
+            return Err(PyErr::new::<PyRuntimeError, _>("Cannot iterate without entering context"));
+        }
+        if self.iterator_called {
+            return Err(PyErr::new::<PyRuntimeError, _>("Iterator already called"));
+        }
+        if chunk_size <= 0 {
+            return Err(PyErr::new::<PyValueError, _>(format!("Chunk size must be positive, got {}", chunk_size)));
+        }
+        let reader = match &mut self.reader {
+            Some(reader) => reader,
+            None => return Err(PyErr::new::<PyRuntimeError, _>("Reader is closed")),
+        };
+        self.iterator_called = true;
+        let mut chunks: BTreeMap<(i32, i32, i32), Vec<Block>> = BTreeMap::new();
+        while let Some(blocks) = reader.read_next(1024 * 8).map_err(schematic_error_to_py)? {
+            for block in blocks {
+                chunks.entry(block.position.chunk_coords(chunk_size)).or_default().push(block);
+            }
+        }
+        Ok(ChunkIterator { chunks: chunks.into_iter() })
+    }
+
+    /// Reads the whole schematic into a dense `(dx, dy, dz)` uint32 array of palette indices plus the palette.
+    /// Index 0 is always air. Array index `[x, y, z]` is relative to the boundary min, so the array
+    /// costs four bytes per cell of the boundary regardless of how many blocks are set.
+    /// Schematics without a declared boundary are sized to their content.
+    /// The reader is consumed afterwards, like after iterating.
+    #[pyo3(name = "to_numpy")]
+    fn read_numpy<'py>(&mut self, py: Python<'py>) -> PyResult<(Bound<'py, PyArray3<u32>>, Vec<String>)> {
+        if !self.entered {
+            return Err(PyErr::new::<PyRuntimeError, _>("Cannot read without entering context"));
+        }
+        if self.iterator_called {
+            return Err(PyErr::new::<PyRuntimeError, _>("Cannot read into numpy after iterating"));
+        }
+        let reader = match &mut self.reader {
+            Some(reader) => reader,
+            None => return Err(PyErr::new::<PyRuntimeError, _>("Reader is closed")),
+        };
+        self.iterator_called = true;
+        let schematic = Schematic::from_reader(reader.as_mut()).map_err(schematic_error_to_py)?;
+        let boundary = *schematic.boundary();
+
+        let mut palette = vec![BlockState::air().to_string()];
+        let mut palette_indices: HashMap<Rc<BlockState>, u32> = HashMap::new();
+        let mut indices = Vec::with_capacity(boundary.volume());
+        for (_, state) in schematic.blocks().iterate_all(AxisOrder::XYZ) {
+            let index = if state.is_air() {
+                0
+            } else {
+                *palette_indices.entry(state.clone()).or_insert_with(|| {
+                    palette.push(state.to_string());
+                    palette.len() as u32 - 1
+                })
+            };
+            indices.push(index);
+        }
+        let shape = [boundary.d_x as usize, boundary.d_y as usize, boundary.d_z as usize];
+        let array = PyArray1::from_vec(py, indices).reshape(shape)?;
+        Ok((array, palette))
+    }
+
     fn read_full(&mut self) -> PyResult<Vec<PyBlock>> {
         if !self.entered {
             return Err(PyErr::new::<PyRuntimeError, _>("Cannot read without entering context"));
@@ -192,7 +286,7 @@ impl VoxelReader {
         }
         if let Some(reader) = &mut self.reader {
             let result = reader.read_to_end_into_vec();
-            result.map_err(|e| PyErr::new::<PyRuntimeError, _>(e))
+            result.map_err(schematic_error_to_py)
                 .map(|blocks| {
                     blocks.into_iter()
                         .map(|b| { PyBlock::from(b) })
@@ -203,32 +297,39 @@ impl VoxelReader {
         }
     }
 
-    #[pyo3(signature = (output, format="vxl"))]
-    fn save(&mut self, output: Bound<'_, PyAny>, format: &str) -> PyResult<()> {
+    #[pyo3(signature = (output, format="vxl", compression_algo="gzip"))]
+    fn save(&mut self, output: Bound<'_, PyAny>, format: &str, compression_algo: &str) -> PyResult<()> {
         if self.reader.is_none() {
             return Err(PyErr::new::<PyRuntimeError, _>("Reader is closed"));
         }
-        let stream = BufWriter::new(GzEncoder::new(BufWriter::new(writer_from(&output)?), flate2::Compression::default()));
+        let format = format.to_ascii_uppercase();
+        if format == "CSV" {
+            // tabular exports stay uncompressed so they can be opened directly
+            let stream = BufWriter::new(writer_from(&output)?);
+            return if let Some(reader) = &mut self.reader {
+                reader.transfer_into(Box::new(CsvSchematicOutputStream::new(stream))).map_err(schematic_error_to_py)
+            } else {
+                Err(PyErr::new::<PyRuntimeError, _>("Reader is closed"))
+            };
+        }
+        let compression = CompressionAlgo::from_name(compression_algo).map_err(schematic_error_to_py)?;
+        let stream = BufWriter::new(compression.encoder(BufWriter::new(writer_from(&output)?)).map_err(schematic_error_to_py)?);
         let boundary = self.boundary()?.into();
 
-        let output_schematic_stream: Box<dyn SchematicOutputStream> = match format.to_ascii_uppercase().as_str() {
-            "VXL" => {
-                Box::new(VXLSchematicOutputStream::new(stream, AxisOrder::preferred(), boundary))
-            },
-            "MOJANG" => {
-                Box::new(MojangSchematicOutputStream::new(stream))
-            },
-            "SPONGE" => {
-                Box::new(SpongeSchematicOutputStream::new(stream, boundary))
-            },
+        let schematic_format = match format.as_str() {
+            "VXL" => SchematicFormat::Vxl,
+            "MOJANG" => SchematicFormat::Mojang,
+            "SPONGE" => SchematicFormat::Sponge,
             "AUTO" => {
                 return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>("Must specify a concrete type when saving"));
             },
             _ => return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Unknown format: {}", format))),
         };
+        let output_schematic_stream = output_stream_for(stream, schematic_format, Some(boundary))
+            .map_err(schematic_error_to_py)?;
 
         if let Some(reader) = &mut self.reader {
-            reader.transfer_into(output_schematic_stream).map_err(|e| PyErr::new::<PyRuntimeError, _>(e))?;
+            reader.transfer_into(output_schematic_stream).map_err(schematic_error_to_py)?;
             Ok(())
         } else {
             Err(PyErr::new::<PyRuntimeError, _>("Reader is closed"))
@@ -241,8 +342,28 @@ impl VoxelReader {
     }
 }
 
+#[pyclass(unsendable)]
+pub struct ChunkIterator {
+    chunks: std::collections::btree_map::IntoIter<(i32, i32, i32), Vec<Block>>,
+}
+
+#[pymethods]
+impl ChunkIterator {
+    fn __iter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<((i32, i32, i32), Vec<PyBlock>)> {
+        self.chunks.next().map(|(chunk, blocks)| {
+            (chunk, blocks.into_iter().map(PyBlock::from).collect())
+        })
+    }
+}
+
+/// Opens a schematic for reading. Compression is detected from the magic bytes unless `compression_algo` is given.
 #[pyfunction]
-pub fn open(input: &Bound<'_, PyAny>) -> PyResult<VoxelReader> {
+#[pyo3(signature = (input, compression_algo=None))]
+pub fn open(input: &Bound<'_, PyAny>, compression_algo: Option<&str>) -> PyResult<VoxelReader> {
     // see if input has a "type" attribute that is of type SchematicType (in python)
     let type_name = input.getattr("type").ok().and_then(|t| {
         if t.is_instance_of::<PyString>() {
@@ -260,7 +381,12 @@ pub fn open(input: &Bound<'_, PyAny>) -> PyResult<VoxelReader> {
             None
         }
     }).unwrap_or_else(|| "auto".to_string()).to_ascii_uppercase();
-    let stream = BufReader::new(GzDecoder::new(BufReader::new(reader_from(input)?)));
+    let input = BufReader::new(reader_from(input)?);
+    let decoded = match compression_algo {
+        Some(name) => CompressionAlgo::from_name(name).and_then(|algo| algo.decoder(input)),
+        None => detect_decoder(input),
+    };
+    let stream = BufReader::new(decoded.map_err(schematic_error_to_py)?);
     match type_name.as_str() {
         "VXL" => {
             Ok(VoxelReader::new(
@@ -285,3 +411,78 @@ pub fn open(input: &Bound<'_, PyAny>) -> PyResult<VoxelReader> {
         _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Unknown type: {}", type_name))),
     }
 }
+
+/// Streams the non-air cells of a dense index grid, as built by `from_numpy`.
+struct DenseArrayInputStream {
+    indices: Vec<u32>,
+    palette: Vec<Rc<BlockState>>,
+    boundary: Boundary,
+    cursor: usize,
+}
+
+impl SchematicInputStream for DenseArrayInputStream {
+    fn read(&mut self, buffer: &mut Vec<Block>, _offset: usize, length: usize) -> Result<Option<usize>, SchematicError> {
+        if self.cursor >= self.indices.len() {
+            return Ok(None);
+        }
+        let mut read = 0;
+        let mut positions = self.boundary.iter(AxisOrder::XYZ).skip(self.cursor);
+        while read < length && self.cursor < self.indices.len() {
+            let position: BlockPosition = positions.next().ok_or("Dense array is larger than its boundary")?;
+            let state = &self.palette[self.indices[self.cursor] as usize];
+            self.cursor += 1;
+            if !state.is_air() {
+                buffer.push(Block::new(state.clone(), position));
+                read += 1;
+            }
+        }
+        Ok(Some(read))
+    }
+
+    fn boundary(&mut self) -> Result<Option<Boundary>, SchematicError> {
+        Ok(Some(self.boundary))
+    }
+}
+
+/// Builds a reader over a dense `(dx, dy, dz)` array of unsigned palette indices, the inverse of `to_numpy`.
+/// Cell `[x, y, z]` is placed at `boundary_min + (x, y, z)`, air cells are skipped.
+#[pyfunction]
+#[pyo3(signature = (array, palette, boundary_min=(0, 0, 0)))]
+pub fn from_numpy(
+    array: &Bound<'_, PyAny>,
+    palette: Vec<String>,
+    boundary_min: (i32, i32, i32),
+) -> PyResult<VoxelReader> {
+    let untyped = array.cast::<PyUntypedArray>()
+        .map_err(|_| PyErr::new::<PyValueError, _>("Expected a numpy array"))?;
+    if untyped.dtype().kind() != b'u' {
+        return Err(PyErr::new::<PyValueError, _>("Array dtype must be an unsigned integer type"));
+    }
+    if untyped.ndim() != 3 {
+        return Err(PyErr::new::<PyValueError, _>(format!("Array must be three-dimensional, got {} dimensions", untyped.ndim())));
+    }
+    let converted = array.call_method1("astype", ("uint32",))?;
+    let typed = converted.cast::<PyArray3<u32>>()?.readonly();
+    let view = typed.as_array();
+    let (d_x, d_y, d_z) = view.dim();
+    let indices: Vec<u32> = view.iter().copied().collect();
+    if let Some(max) = indices.iter().max() {
+        if *max as usize >= palette.len() {
+            return Err(PyErr::new::<PyValueError, _>(format!("Index {} is outside the palette of size {}", max, palette.len())));
+        }
+    }
+    let palette = palette.into_iter()
+        .map(|state| BlockState::from_string(state).map(Rc::new))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(PyErr::new::<PyValueError, _>)?;
+    let boundary = Boundary::new(
+        boundary_min.0, boundary_min.1, boundary_min.2,
+        d_x as i32, d_y as i32, d_z as i32,
+    );
+    Ok(VoxelReader::new(Box::new(DenseArrayInputStream {
+        indices,
+        palette,
+        boundary,
+        cursor: 0,
+    })))
+}
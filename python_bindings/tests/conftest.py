@@ -0,0 +1,6 @@
+import os
+import pytest
+
+@pytest.fixture
+def sponge_sample():
+  return os.path.join(os.path.dirname(__file__), "../../core/src/stream/test_schematics/tree.sponge")
@@ -0,0 +1,26 @@
+import numpy as np
+import pytest
+import voxels_rs
+
+def test_from_numpy_round_trip():
+  palette = ["minecraft:air", "minecraft:stone", "minecraft:oak_log[axis=y]"]
+  array = np.zeros((4, 3, 5), dtype=np.uint8)
+  array[0, 0, 0] = 1
+  array[3, 2, 4] = 2
+  array[1, :, 2] = 1
+
+  with voxels_rs.from_numpy(array, palette, boundary_min=(10, -5, 3)) as schematic:
+    boundary = schematic.boundary()
+    assert (boundary.min_x(), boundary.min_y(), boundary.min_z()) == (10, -5, 3)
+    round_tripped, round_trip_palette = schematic.to_numpy()
+
+  assert round_tripped.shape == array.shape
+  expected = np.array(palette)[array]
+  actual = np.array(round_trip_palette)[round_tripped]
+  assert (expected == actual).all()
+
+def test_from_numpy_validation():
+  with pytest.raises(ValueError):
+    voxels_rs.from_numpy(np.zeros((2, 2, 2), dtype=np.float32), ["minecraft:air"])
+  with pytest.raises(ValueError):
+    voxels_rs.from_numpy(np.full((2, 2, 2), 3, dtype=np.uint32), ["minecraft:air"])
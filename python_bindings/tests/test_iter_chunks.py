@@ -0,0 +1,24 @@
+import os
+import tempfile
+import voxels_rs
+
+def vxl_sample(directory, sponge_sample):
+  path = os.path.join(directory, "tree.vxl")
+  with voxels_rs.open(sponge_sample) as schematic:
+    schematic.save(path, format="vxl")
+  return path
+
+def test_iter_chunks(sponge_sample):
+  with tempfile.TemporaryDirectory() as directory:
+    sample = vxl_sample(directory, sponge_sample)
+    with voxels_rs.open(sample) as schematic:
+      total = 0
+      for (cx, cy, cz), blocks in schematic.iter_chunks(4):
+        assert blocks
+        for block in blocks:
+          position = block.position()
+          assert (position.x() // 4, position.y() // 4, position.z() // 4) == (cx, cy, cz)
+        total += len(blocks)
+
+    with voxels_rs.open(sample) as schematic:
+      assert total == len(schematic.read_full())
@@ -0,0 +1,21 @@
+import numpy as np
+import pytest
+import voxels_rs
+
+def test_to_numpy(sponge_sample):
+  with voxels_rs.open(sponge_sample) as schematic:
+    boundary = schematic.boundary()
+    array, palette = schematic.to_numpy()
+  assert array.dtype == np.uint32
+  assert array.shape == boundary.size()
+  assert palette[0] == "minecraft:air"
+  assert int(array.max()) < len(palette)
+  assert np.count_nonzero(array) > 0
+
+def test_to_numpy_consumes_reader(sponge_sample):
+  with voxels_rs.open(sponge_sample) as schematic:
+    schematic.to_numpy()
+    with pytest.raises(RuntimeError):
+      schematic.to_numpy()
+    with pytest.raises(RuntimeError):
+      schematic.read_full()
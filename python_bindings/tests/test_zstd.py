@@ -0,0 +1,22 @@
+import os
+import tempfile
+import voxels_rs
+
+ZSTD_MAGIC = b"\x28\xb5\x2f\xfd"
+
+def test_zstd_round_trip(sponge_sample):
+  with tempfile.TemporaryDirectory() as directory:
+    gzip_path = os.path.join(directory, "tree.vxl.gz")
+    zstd_path = os.path.join(directory, "tree.vxl.zst")
+    with voxels_rs.open(sponge_sample) as schematic:
+      schematic.save(gzip_path, format="vxl")
+    with voxels_rs.open(sponge_sample) as schematic:
+      schematic.save(zstd_path, format="vxl", compression_algo="zstd")
+
+    with open(zstd_path, "rb") as f:
+      assert f.read(4) == ZSTD_MAGIC
+
+    with voxels_rs.open(gzip_path) as schematic:
+      expected = [str(block) for block in schematic.read_full()]
+    with voxels_rs.open(zstd_path) as schematic:
+      assert [str(block) for block in schematic.read_full()] == expected